@@ -1,6 +1,6 @@
 use anyhow::Context;
-use domo_arigato::auth::authenticate;
-use domo_arigato::state::connect;
+use domo_arigato::auth::{authenticate, Authentication};
+use domo_arigato::state::{connect, Event, TickLoop};
 use std::env;
 use std::io::{stdin, stdout, BufRead, Write};
 
@@ -17,18 +17,38 @@ fn main() -> anyhow::Result<()> {
     let handle = stdin.lock();
     let mut lines = handle.lines();
 
-    print!("Mojang account ID: ");
+    print!("Mojang account ID, or username for offline mode: ");
     stdout().flush()?;
     let account_id = lines.next().context("EOF")??;
-    print!("Password: ");
+    print!("Password (empty for offline mode): ");
     stdout().flush()?;
     let password = lines.next().context("EOF")??;
 
-    let authentication = authenticate(&account_id, &password)?;
-    connect(host, port, 751)?
+    let mut authentication = if password.is_empty() {
+        Authentication::offline(&account_id)
+    } else {
+        authenticate(&account_id, &password)?
+    };
+    let play = connect(host, port, 751)?
         .login()?
-        .login(&authentication)?
-        .run()?;
+        .login(&mut authentication)?;
 
-    Ok(())
+    TickLoop::new(play).run(|play, event| {
+        match event {
+            Event::ChatReceived { message, .. } => {
+                println!("{}", message.0);
+            }
+            Event::Died { .. } => {
+                play.respawn()?;
+            }
+            Event::PacketSkipped { frame, reason } => {
+                eprintln!("Skipped packet {:#04x}: {}", frame.id, reason);
+            }
+            Event::Disconnected { reason } => {
+                println!("Disconnected: {}", reason.0);
+            }
+            _ => {}
+        }
+        Ok(())
+    })
 }
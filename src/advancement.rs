@@ -0,0 +1,176 @@
+use crate::proto::play::{Advancement, AdvancementEntry, AdvancementProgress};
+use crate::proto::types::Identifier;
+use std::collections::{HashMap, HashSet};
+
+/// The advancements sent by the server and the player's progress on them.
+#[derive(Debug, Clone, Default)]
+pub struct AdvancementTracker {
+    advancements: HashMap<String, Advancement>,
+    /// Achieved criteria of each advancement.
+    progress: HashMap<String, HashSet<String>>,
+}
+
+impl AdvancementTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Advancement> {
+        self.advancements.get(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Advancement)> {
+        self.advancements
+            .iter()
+            .map(|(id, advancement)| (id.as_str(), advancement))
+    }
+
+    pub fn is_achieved(&self, id: &str, criterion: &str) -> bool {
+        self.progress
+            .get(id)
+            .is_some_and(|achieved| achieved.contains(criterion))
+    }
+
+    /// Whether every requirement of the advancement has at least one achieved criterion.
+    pub fn is_completed(&self, id: &str) -> bool {
+        let (advancement, achieved) = match (self.advancements.get(id), self.progress.get(id)) {
+            (Some(advancement), Some(achieved)) => (advancement, achieved),
+            _ => return false,
+        };
+        !advancement.requirements.is_empty()
+            && advancement.requirements.iter().all(|requirement| {
+                requirement
+                    .0
+                    .iter()
+                    .any(|criterion| achieved.contains(&criterion.0))
+            })
+    }
+
+    /// IDs of all completed advancements.
+    pub fn completed(&self) -> impl Iterator<Item = &str> {
+        self.advancements
+            .keys()
+            .filter(move |id| self.is_completed(id))
+            .map(String::as_str)
+    }
+
+    /// Applies an `Advancements` packet, returning the IDs of advancements it completed.
+    ///
+    /// A reset replaces everything, and nothing it contains is reported as newly completed.
+    pub fn update(
+        &mut self,
+        reset: bool,
+        advancements: &[AdvancementEntry],
+        removed: &[Identifier],
+        progress: &[AdvancementProgress],
+    ) -> Vec<String> {
+        if reset {
+            self.advancements.clear();
+            self.progress.clear();
+        }
+        for entry in advancements {
+            self.advancements
+                .insert(entry.id.0 .0.clone(), entry.advancement.clone());
+        }
+        for id in removed {
+            self.advancements.remove(&id.0 .0);
+            self.progress.remove(&id.0 .0);
+        }
+
+        let mut completed = Vec::new();
+        for entry in progress {
+            let id = &entry.id.0 .0;
+            let was_completed = self.is_completed(id);
+            let achieved = self.progress.entry(id.clone()).or_default();
+            for criterion in &entry.criteria {
+                if criterion.achieved {
+                    achieved.insert(criterion.id.0 .0.clone());
+                } else {
+                    achieved.remove(&criterion.id.0 .0);
+                }
+            }
+            if !reset && !was_completed && self.is_completed(id) {
+                completed.push(id.clone());
+            }
+        }
+        completed
+    }
+
+    pub fn clear(&mut self) {
+        self.advancements.clear();
+        self.progress.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::play::{AdvancementRequirement, CriterionProgress};
+    use crate::test_util::identifier;
+
+    fn progress(id: &str, criteria: &[(&str, bool)]) -> AdvancementProgress {
+        AdvancementProgress {
+            id: identifier(id),
+            criteria: criteria
+                .iter()
+                .map(|&(criterion, achieved)| CriterionProgress {
+                    id: identifier(criterion),
+                    achieved,
+                    date_of_achieving: if achieved { Some(0) } else { None },
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn completion() {
+        // Requires "log" and either "stone" or "iron".
+        let advancement = Advancement {
+            has_parent: false,
+            parent_id: None,
+            has_display: false,
+            display: None,
+            criteria: vec![identifier("log"), identifier("stone"), identifier("iron")],
+            requirements: vec![
+                AdvancementRequirement(vec!["log".to_string().into()]),
+                AdvancementRequirement(vec!["stone".to_string().into(), "iron".to_string().into()]),
+            ],
+        };
+        let entries = [AdvancementEntry {
+            id: identifier("story/root"),
+            advancement,
+        }];
+
+        let mut tracker = AdvancementTracker::new();
+        let completed = tracker.update(
+            true,
+            &entries,
+            &[],
+            &[progress("story/root", &[("log", true)])],
+        );
+        assert!(completed.is_empty());
+        assert!(tracker.is_achieved("story/root", "log"));
+        assert!(!tracker.is_completed("story/root"));
+
+        let completed = tracker.update(
+            false,
+            &[],
+            &[],
+            &[progress("story/root", &[("iron", true)])],
+        );
+        assert_eq!(completed, vec!["story/root".to_string()]);
+        assert_eq!(tracker.completed().collect::<Vec<_>>(), vec!["story/root"]);
+
+        let completed = tracker.update(
+            false,
+            &[],
+            &[],
+            &[progress("story/root", &[("stone", true)])],
+        );
+        assert!(completed.is_empty());
+
+        tracker.update(false, &[], &[identifier("story/root")], &[]);
+        assert!(tracker.get("story/root").is_none());
+        assert!(!tracker.is_completed("story/root"));
+    }
+}
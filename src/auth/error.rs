@@ -0,0 +1,152 @@
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+
+/// Why the authentication or session server refused a request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthError {
+    /// Wrong username or password.
+    InvalidCredentials,
+    /// The access token has expired or been invalidated.
+    InvalidToken,
+    /// The Mojang account has been migrated to a Microsoft account.
+    Migrated,
+    /// Too many requests were made recently.
+    RateLimited,
+    InsufficientPrivileges {
+        message: String,
+    },
+    /// Multiplayer is disabled in the account's settings.
+    MultiplayerDisabled,
+    Banned {
+        message: String,
+    },
+    /// Any other error, as sent by the server.
+    Other {
+        status: u16,
+        error: String,
+        message: String,
+    },
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorBody {
+    #[serde(default)]
+    error: String,
+    #[serde(default)]
+    error_message: String,
+    #[serde(default)]
+    cause: String,
+}
+
+impl AuthError {
+    /// Classifies an error response from Yggdrasil or the session server.
+    pub fn parse(status: u16, body: &str) -> Self {
+        let body: ErrorBody = serde_json::from_str(body).unwrap_or_default();
+        let message = body.error_message.to_lowercase();
+        if status == 429 || body.error == "TooManyRequestsException" {
+            Self::RateLimited
+        } else if body.cause == "UserMigratedException" || message.contains("migrated") {
+            Self::Migrated
+        } else if body.error == "UserBannedException" {
+            Self::Banned {
+                message: body.error_message,
+            }
+        } else if body.error == "InsufficientPrivilegesException" {
+            if message.contains("multiplayer") {
+                Self::MultiplayerDisabled
+            } else {
+                Self::InsufficientPrivileges {
+                    message: body.error_message,
+                }
+            }
+        } else if message.contains("invalid token") {
+            Self::InvalidToken
+        } else if body.error == "ForbiddenOperationException"
+            && message.contains("invalid credentials")
+        {
+            Self::InvalidCredentials
+        } else {
+            Self::Other {
+                status,
+                error: body.error,
+                message: body.error_message,
+            }
+        }
+    }
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidCredentials => write!(f, "invalid username or password"),
+            Self::InvalidToken => write!(f, "the access token is invalid or expired"),
+            Self::Migrated => write!(f, "the account has been migrated to a Microsoft account"),
+            Self::RateLimited => write!(f, "too many requests, try again later"),
+            Self::InsufficientPrivileges { message } => {
+                write!(f, "insufficient privileges: {}", message)
+            }
+            Self::MultiplayerDisabled => write!(f, "multiplayer is disabled for the account"),
+            Self::Banned { message } => write!(f, "the account is banned: {}", message),
+            Self::Other {
+                status,
+                error,
+                message,
+            } => write!(f, "server returned {} {}: {}", status, error, message),
+        }
+    }
+}
+
+impl Error for AuthError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let cases = [
+            (
+                403,
+                r#"{"error":"ForbiddenOperationException","errorMessage":"Invalid credentials. Invalid username or password."}"#,
+                AuthError::InvalidCredentials,
+            ),
+            (
+                403,
+                r#"{"error":"ForbiddenOperationException","errorMessage":"Invalid token."}"#,
+                AuthError::InvalidToken,
+            ),
+            (
+                410,
+                r#"{"error":"GoneException","errorMessage":"Migrated","cause":"UserMigratedException"}"#,
+                AuthError::Migrated,
+            ),
+            (429, "", AuthError::RateLimited),
+            (
+                403,
+                r#"{"error":"InsufficientPrivilegesException","errorMessage":"Multiplayer is disabled."}"#,
+                AuthError::MultiplayerDisabled,
+            ),
+            (
+                403,
+                r#"{"error":"UserBannedException","errorMessage":"You are banned."}"#,
+                AuthError::Banned {
+                    message: "You are banned.".to_string(),
+                },
+            ),
+            (
+                500,
+                "not json",
+                AuthError::Other {
+                    status: 500,
+                    error: String::new(),
+                    message: String::new(),
+                },
+            ),
+        ];
+        for (status, body, expected) in cases.iter() {
+            assert_eq!(&AuthError::parse(*status, body), expected);
+        }
+    }
+}
@@ -0,0 +1,373 @@
+//! Authentication with a Microsoft account, through the OAuth device code flow.
+//!
+//! The Microsoft access token is exchanged for an Xbox Live token, then an XSTS token for
+//! Minecraft services, and finally a Minecraft access token.
+
+use super::Authentication;
+use anyhow::Context;
+use reqwest::blocking::{Client, Response};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Client ID and endpoints of the Microsoft flow. The URLs can be pointed at a mock server.
+#[derive(Debug, Clone)]
+pub struct MicrosoftConfig {
+    /// The ID of the Azure application requesting access.
+    pub client_id: String,
+    pub scope: String,
+    /// The OAuth 2.0 endpoint, ending in `/oauth2/v2.0`.
+    pub oauth_url: String,
+    pub xbox_live_url: String,
+    pub xsts_url: String,
+    pub minecraft_services_url: String,
+    pub client: Client,
+}
+
+impl MicrosoftConfig {
+    pub fn new(client_id: &str) -> Self {
+        Self {
+            client_id: client_id.to_string(),
+            scope: "XboxLive.signin offline_access".to_string(),
+            oauth_url: "https://login.microsoftonline.com/consumers/oauth2/v2.0".to_string(),
+            xbox_live_url: "https://user.auth.xboxlive.com".to_string(),
+            xsts_url: "https://xsts.auth.xboxlive.com".to_string(),
+            minecraft_services_url: "https://api.minecraftservices.com".to_string(),
+            client: Client::new(),
+        }
+    }
+}
+
+/// A code the user enters at `verification_uri` to approve the login.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DeviceCode {
+    pub user_code: String,
+    pub verification_uri: String,
+    /// Instructions for the user, from Microsoft.
+    pub message: String,
+    device_code: String,
+    expires_in: u64,
+    interval: u64,
+}
+
+/// Microsoft's OAuth tokens.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MicrosoftToken {
+    pub access_token: String,
+    /// Exchanged for a new token by `refresh_token`, without asking the user again.
+    pub refresh_token: Option<String>,
+}
+
+/// Logs in with a Microsoft account, calling `prompt` with the code the user has to enter.
+pub fn authenticate_microsoft<F>(
+    config: &MicrosoftConfig,
+    prompt: F,
+) -> anyhow::Result<Authentication>
+where
+    F: FnOnce(&DeviceCode),
+{
+    let device_code = request_device_code(config)?;
+    prompt(&device_code);
+    let token = poll_device_code(config, &device_code)?;
+    login_with_microsoft(config, &token.access_token)
+}
+
+pub fn request_device_code(config: &MicrosoftConfig) -> anyhow::Result<DeviceCode> {
+    let response = config
+        .client
+        .post(&format!("{}/devicecode", config.oauth_url))
+        .form(&[
+            ("client_id", config.client_id.as_str()),
+            ("scope", config.scope.as_str()),
+        ])
+        .send()?;
+    parse(response, "device code request failed")
+}
+
+/// Waits for the user to approve a device code.
+pub fn poll_device_code(
+    config: &MicrosoftConfig,
+    device_code: &DeviceCode,
+) -> anyhow::Result<MicrosoftToken> {
+    let deadline = Instant::now() + Duration::from_secs(device_code.expires_in);
+    let mut interval = Duration::from_secs(device_code.interval);
+    loop {
+        let response = config
+            .client
+            .post(&format!("{}/token", config.oauth_url))
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                ("client_id", config.client_id.as_str()),
+                ("device_code", device_code.device_code.as_str()),
+            ])
+            .send()?;
+        if response.status().is_success() {
+            return Ok(response.json()?);
+        }
+
+        let error: OAuthError = response.json().context("device code polling failed")?;
+        match error.error.as_str() {
+            "authorization_pending" => {}
+            "slow_down" => interval += Duration::from_secs(5),
+            _ => {
+                return Err(anyhow::Error::msg(format!(
+                    "device code login failed: {}",
+                    error.error_description.unwrap_or(error.error)
+                )))
+            }
+        }
+        if Instant::now() + interval > deadline {
+            return Err(anyhow::Error::msg("device code expired"));
+        }
+        thread::sleep(interval);
+    }
+}
+
+pub fn refresh_token(
+    config: &MicrosoftConfig,
+    refresh_token: &str,
+) -> anyhow::Result<MicrosoftToken> {
+    let response = config
+        .client
+        .post(&format!("{}/token", config.oauth_url))
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("client_id", config.client_id.as_str()),
+            ("scope", config.scope.as_str()),
+            ("refresh_token", refresh_token),
+        ])
+        .send()?;
+    parse(response, "token refresh failed")
+}
+
+/// Exchanges a Microsoft access token for a Minecraft one, through Xbox Live.
+pub fn login_with_microsoft(
+    config: &MicrosoftConfig,
+    microsoft_access_token: &str,
+) -> anyhow::Result<Authentication> {
+    let client = &config.client;
+
+    let response = client
+        .post(&format!("{}/user/authenticate", config.xbox_live_url))
+        .json(&json!({
+            "Properties": {
+                "AuthMethod": "RPS",
+                "SiteName": "user.auth.xboxlive.com",
+                "RpsTicket": format!("d={}", microsoft_access_token),
+            },
+            "RelyingParty": "http://auth.xboxlive.com",
+            "TokenType": "JWT",
+        }))
+        .send()?;
+    let xbox_live: XboxToken = parse(response, "Xbox Live authentication failed")?;
+
+    let response = client
+        .post(&format!("{}/xsts/authorize", config.xsts_url))
+        .json(&json!({
+            "Properties": {
+                "SandboxId": "RETAIL",
+                "UserTokens": [xbox_live.token],
+            },
+            "RelyingParty": "rp://api.minecraftservices.com/",
+            "TokenType": "JWT",
+        }))
+        .send()?;
+    if response.status().as_u16() == 401 {
+        let error: XstsError = response.json().context("XSTS authorization failed")?;
+        return Err(anyhow::Error::msg(xsts_error_message(error.x_err)));
+    }
+    let xsts: XboxToken = parse(response, "XSTS authorization failed")?;
+    let user_hash = xsts
+        .display_claims
+        .xui
+        .first()
+        .map(|claim| claim.uhs.as_str())
+        .context("XSTS token has no user hash")?;
+
+    let response = client
+        .post(&format!(
+            "{}/authentication/login_with_xbox",
+            config.minecraft_services_url
+        ))
+        .json(&json!({
+            "identityToken": format!("XBL3.0 x={};{}", user_hash, xsts.token),
+        }))
+        .send()?;
+    let minecraft: MinecraftToken = parse(response, "Minecraft login failed")?;
+
+    let response = client
+        .get(&format!(
+            "{}/minecraft/profile",
+            config.minecraft_services_url
+        ))
+        .bearer_auth(&minecraft.access_token)
+        .send()?;
+    if response.status().as_u16() == 404 {
+        return Err(anyhow::Error::msg("the account does not own Minecraft"));
+    }
+    let profile: super::Profile = parse(response, "profile request failed")?;
+
+    Ok(Authentication {
+        uuid: profile.id,
+        name: profile.name,
+        access_token: Some(minecraft.access_token),
+        client_token: None,
+    })
+}
+
+fn parse<T>(response: Response, context: &'static str) -> anyhow::Result<T>
+where
+    T: DeserializeOwned,
+{
+    response.error_for_status_ref().context(context)?;
+    response.json().context(context)
+}
+
+fn xsts_error_message(code: u64) -> String {
+    match code {
+        2148916233 => "the Microsoft account has no Xbox account".to_string(),
+        2148916235 => "Xbox Live is not available in the account's country".to_string(),
+        2148916236 | 2148916237 => "the account needs adult verification".to_string(),
+        2148916238 => "the account belongs to a child and must be added to a family".to_string(),
+        _ => format!("XSTS authorization failed with error {}", code),
+    }
+}
+
+#[derive(Deserialize)]
+struct OAuthError {
+    error: String,
+    error_description: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct XboxToken {
+    token: String,
+    display_claims: DisplayClaims,
+}
+
+#[derive(Deserialize)]
+struct DisplayClaims {
+    xui: Vec<UserClaim>,
+}
+
+#[derive(Deserialize)]
+struct UserClaim {
+    uhs: String,
+}
+
+#[derive(Deserialize)]
+struct XstsError {
+    #[serde(rename = "XErr")]
+    x_err: u64,
+}
+
+#[derive(Deserialize)]
+struct MinecraftToken {
+    access_token: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::test_server::{route, TestServer};
+
+    #[test]
+    fn device_code_flow() {
+        let server = TestServer::start(vec![
+            route(
+                "/oauth/devicecode",
+                200,
+                json!({
+                    "device_code": "device",
+                    "user_code": "ABCD-EFGH",
+                    "verification_uri": "https://microsoft.com/link",
+                    "message": "Enter ABCD-EFGH",
+                    "expires_in": 900,
+                    "interval": 0,
+                }),
+            ),
+            route(
+                "/oauth/token",
+                400,
+                json!({"error": "authorization_pending"}),
+            ),
+            route(
+                "/oauth/token",
+                200,
+                json!({"access_token": "microsoft", "refresh_token": "refresh"}),
+            ),
+            route(
+                "/user/authenticate",
+                200,
+                json!({"Token": "xbl", "DisplayClaims": {"xui": [{"uhs": "hash"}]}}),
+            ),
+            route(
+                "/xsts/authorize",
+                200,
+                json!({"Token": "xsts", "DisplayClaims": {"xui": [{"uhs": "hash"}]}}),
+            ),
+            route(
+                "/authentication/login_with_xbox",
+                200,
+                json!({"access_token": "minecraft", "expires_in": 86400}),
+            ),
+            route(
+                "/minecraft/profile",
+                200,
+                json!({"id": "069a79f444e94726a5befca90e38aaf5", "name": "Notch"}),
+            ),
+        ]);
+        let config = MicrosoftConfig {
+            oauth_url: format!("{}/oauth", server.url()),
+            xbox_live_url: server.url().to_string(),
+            xsts_url: server.url().to_string(),
+            minecraft_services_url: server.url().to_string(),
+            ..MicrosoftConfig::new("client")
+        };
+
+        let mut user_code = None;
+        let auth = authenticate_microsoft(&config, |code| {
+            user_code = Some(code.user_code.clone());
+        })
+        .unwrap();
+        assert_eq!(user_code.as_deref(), Some("ABCD-EFGH"));
+        assert_eq!(auth.uuid(), "069a79f444e94726a5befca90e38aaf5");
+        assert_eq!(auth.name(), "Notch");
+        assert_eq!(auth.access_token(), Some("minecraft"));
+
+        let requests = server.requests();
+        assert!(requests[0].body.contains("client_id=client"));
+        assert_eq!(requests[3].json()["Properties"]["RpsTicket"], "d=microsoft");
+        assert_eq!(requests[4].json()["Properties"]["UserTokens"][0], "xbl");
+        assert_eq!(requests[5].json()["identityToken"], "XBL3.0 x=hash;xsts");
+        assert_eq!(
+            requests[6].header("authorization"),
+            Some("Bearer minecraft")
+        );
+    }
+
+    #[test]
+    fn xsts_error() {
+        let server = TestServer::start(vec![
+            route(
+                "/user/authenticate",
+                200,
+                json!({"Token": "xbl", "DisplayClaims": {"xui": [{"uhs": "hash"}]}}),
+            ),
+            route("/xsts/authorize", 401, json!({"XErr": 2148916238u64})),
+        ]);
+        let config = MicrosoftConfig {
+            xbox_live_url: server.url().to_string(),
+            xsts_url: server.url().to_string(),
+            ..MicrosoftConfig::new("client")
+        };
+        let error = match login_with_microsoft(&config, "microsoft") {
+            Ok(_) => panic!("login succeeded"),
+            Err(error) => error,
+        };
+        assert!(error.to_string().contains("child"));
+    }
+}
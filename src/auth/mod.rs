@@ -0,0 +1,295 @@
+mod error;
+mod microsoft;
+mod service;
+#[cfg(test)]
+pub(crate) mod test_server;
+
+pub use self::error::AuthError;
+pub use self::microsoft::{
+    authenticate_microsoft, login_with_microsoft, poll_device_code, refresh_token,
+    request_device_code, DeviceCode, MicrosoftConfig, MicrosoftToken,
+};
+pub use self::service::{
+    server_hash, AuthService, GameProfile, ProfileProperty, SessionService, AUTH_SERVER,
+    SESSION_SERVER,
+};
+
+use crate::proto::types::Uuid;
+use anyhow::Context;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+#[cfg(unix)]
+use std::fs::Permissions;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Authentication {
+    uuid: String,
+    name: String,
+    /// `None` for offline-mode players, which cannot join online-mode servers.
+    access_token: Option<String>,
+    /// The client the access token was issued to, which is needed to refresh it.
+    client_token: Option<String>,
+}
+
+impl Authentication {
+    pub fn uuid(&self) -> &str {
+        &self.uuid
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.access_token.is_none()
+    }
+
+    pub(crate) fn access_token(&self) -> Option<&str> {
+        self.access_token.as_deref()
+    }
+
+    /// A player for servers running with `online-mode=false`, which only need a username.
+    pub fn offline(name: &str) -> Self {
+        Self {
+            uuid: format!("{:032x}", offline_uuid(name).0),
+            name: name.to_string(),
+            access_token: None,
+            client_token: None,
+        }
+    }
+
+    /// Reads credentials saved by `save`.
+    pub fn load<P>(path: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let file = File::open(path).context("failed to open credential cache")?;
+        serde_json::from_reader(file).context("invalid credential cache")
+    }
+
+    /// Saves the credentials, including the access token, to a file only the current user can
+    /// read.
+    pub fn save<P>(&self, path: P) -> anyhow::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = options
+            .open(path)
+            .context("failed to create credential cache")?;
+        // The mode only applies to new files, so make sure an existing one is private too.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(Permissions::from_mode(0o600))?;
+        }
+        serde_json::to_writer(file, self)?;
+        Ok(())
+    }
+}
+
+/// The UUID that offline-mode servers assign to a player: a version 3 UUID of
+/// `OfflinePlayer:<name>`.
+pub fn offline_uuid(name: &str) -> Uuid {
+    let mut hash = Md5::digest(format!("OfflinePlayer:{}", name).as_bytes());
+    hash[6] = hash[6] & 0x0f | 0x30;
+    hash[8] = hash[8] & 0x3f | 0x80;
+    let mut bytes = [0; 16];
+    bytes.copy_from_slice(&hash);
+    Uuid(u128::from_be_bytes(bytes))
+}
+
+/// Logs in to a Mojang account on `AUTH_SERVER`.
+pub fn authenticate(username: &str, password: &str) -> anyhow::Result<Authentication> {
+    AuthService::default().authenticate(username, password)
+}
+
+/// Whether the access token can still be used to join servers.
+pub fn validate(auth: &Authentication) -> anyhow::Result<bool> {
+    AuthService::default().validate(auth)
+}
+
+/// Replaces the access token with a new one, invalidating the old one.
+///
+/// This needs the client token the access token was issued to.
+pub fn refresh(auth: &mut Authentication) -> anyhow::Result<()> {
+    AuthService::default().refresh(auth)
+}
+
+/// Refreshes the access token if it is no longer valid.
+pub fn ensure_valid(auth: &mut Authentication) -> anyhow::Result<()> {
+    AuthService::default().ensure_valid(auth)
+}
+
+/// Invalidates the access token.
+pub fn invalidate(auth: &Authentication) -> anyhow::Result<()> {
+    AuthService::default().invalidate(auth)
+}
+
+/// Invalidates every access token of an account.
+pub fn signout(username: &str, password: &str) -> anyhow::Result<()> {
+    AuthService::default().signout(username, password)
+}
+
+/// Turns an error response from Yggdrasil or the session server into an `AuthError`.
+pub(crate) fn check_response(
+    response: reqwest::blocking::Response,
+) -> anyhow::Result<reqwest::blocking::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().unwrap_or_default();
+    Err(AuthError::parse(status.as_u16(), &body).into())
+}
+
+/// Like `check_response`, for async responses.
+#[cfg(feature = "async")]
+pub(crate) async fn check_response_async(
+    response: reqwest::Response,
+) -> anyhow::Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(AuthError::parse(status.as_u16(), &body).into())
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    access_token: String,
+    client_token: String,
+    selected_profile: Profile,
+}
+
+impl From<Response> for Authentication {
+    fn from(response: Response) -> Self {
+        Self {
+            uuid: response.selected_profile.id,
+            name: response.selected_profile.name,
+            access_token: Some(response.access_token),
+            client_token: Some(response.client_token),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Profile {
+    id: String,
+    name: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_server::{route, TestServer};
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn offline() {
+        assert_eq!(
+            offline_uuid("Notch"),
+            Uuid(0xb50ad385_829d_3141_a216_7e7d7539ba7f)
+        );
+        let auth = Authentication::offline("Notch");
+        assert_eq!(auth.uuid(), "b50ad385829d3141a2167e7d7539ba7f");
+        assert_eq!(auth.name(), "Notch");
+        assert!(auth.is_offline());
+    }
+
+    fn response(access_token: &str) -> serde_json::Value {
+        json!({
+            "accessToken": access_token,
+            "clientToken": "client",
+            "selectedProfile": {"id": "069a79f444e94726a5befca90e38aaf5", "name": "Notch"},
+        })
+    }
+
+    #[test]
+    fn refreshes_invalid_token() {
+        let server = TestServer::start(vec![
+            route("/authenticate", 200, response("first")),
+            route(
+                "/validate",
+                403,
+                json!({"error": "ForbiddenOperationException", "errorMessage": "Invalid token."}),
+            ),
+            route("/refresh", 200, response("second")),
+            route("/validate", 204, json!({})),
+        ]);
+
+        let service = AuthService::new(server.url());
+        let mut auth = service
+            .authenticate("notch@example.com", "hunter2")
+            .unwrap();
+        assert_eq!(auth.access_token(), Some("first"));
+        service.ensure_valid(&mut auth).unwrap();
+        assert_eq!(auth.access_token(), Some("second"));
+        service.ensure_valid(&mut auth).unwrap();
+        assert_eq!(auth.access_token(), Some("second"));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[2].json()["accessToken"], "first");
+        assert_eq!(requests[2].json()["clientToken"], "client");
+    }
+
+    #[test]
+    fn typed_errors() {
+        let server = TestServer::start(vec![route(
+            "/authenticate",
+            403,
+            json!({
+                "error": "ForbiddenOperationException",
+                "errorMessage": "Invalid credentials. Invalid username or password.",
+            }),
+        )]);
+        let error = match AuthService::new(server.url()).authenticate("notch@example.com", "wrong")
+        {
+            Ok(_) => panic!("authentication succeeded"),
+            Err(error) => error,
+        };
+        assert_eq!(error.downcast_ref(), Some(&AuthError::InvalidCredentials));
+    }
+
+    #[test]
+    fn cache_file() {
+        let path = std::env::temp_dir().join(format!(
+            "domo_arigato_credentials_{}.json",
+            std::process::id()
+        ));
+        let auth = Authentication {
+            uuid: "069a79f444e94726a5befca90e38aaf5".to_string(),
+            name: "Notch".to_string(),
+            access_token: Some("access".to_string()),
+            client_token: Some("client".to_string()),
+        };
+        auth.save(&path).unwrap();
+        let loaded = Authentication::load(&path);
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::metadata(&path).unwrap().permissions().mode()
+        };
+        std::fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.uuid(), auth.uuid());
+        assert_eq!(loaded.access_token(), Some("access"));
+        assert_eq!(loaded.client_token, Some("client".to_string()));
+        #[cfg(unix)]
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
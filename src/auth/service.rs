@@ -0,0 +1,446 @@
+//! Endpoints and HTTP clients of the authentication and session servers.
+//!
+//! Both default to Mojang's servers. Third-party servers, such as those of authlib-injector,
+//! serve the same API under their own root, usually at `<root>/authserver` and
+//! `<root>/sessionserver`.
+
+#[cfg(feature = "async")]
+use super::check_response_async;
+use super::{check_response, AuthError, Authentication, Response};
+use anyhow::Context;
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use std::fmt::Write;
+
+/// The Yggdrasil authentication server used for Mojang accounts.
+pub const AUTH_SERVER: &str = "https://authserver.mojang.com";
+
+/// The session server that clients join and servers verify players with.
+pub const SESSION_SERVER: &str = "https://sessionserver.mojang.com";
+
+/// A Yggdrasil authentication server, which issues and refreshes access tokens.
+///
+/// The client is a blocking `reqwest` client by default. With the `async` feature, a service
+/// with an async `reqwest::Client` can validate and refresh tokens for `AsyncLogin`.
+#[derive(Debug, Clone)]
+pub struct AuthService<C = Client> {
+    pub url: String,
+    /// The client used for every request, which can be configured with a proxy or timeouts.
+    pub client: C,
+}
+
+impl<C> AuthService<C> {
+    pub fn with_client(url: &str, client: C) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            client,
+        }
+    }
+}
+
+impl AuthService {
+    pub fn new(url: &str) -> Self {
+        Self::with_client(url, Client::new())
+    }
+
+    pub fn authenticate(&self, username: &str, password: &str) -> anyhow::Result<Authentication> {
+        let client_token = format!("{:032x}", rand::random::<u128>());
+        let response = self
+            .client
+            .post(&format!("{}/authenticate", self.url))
+            .json(&json!({
+                "agent": {
+                    "name": "Minecraft",
+                    "version": 1
+                },
+                "username": username,
+                "password": password,
+                "clientToken": client_token,
+            }))
+            .send()?;
+        let response: Response = check_response(response)?.json()?;
+        Ok(response.into())
+    }
+
+    /// Whether the access token can still be used to join servers.
+    pub fn validate(&self, auth: &Authentication) -> anyhow::Result<bool> {
+        let response = self
+            .client
+            .post(&format!("{}/validate", self.url))
+            .json(&token_request(auth))
+            .send()?;
+        match check_response(response) {
+            Ok(_) => Ok(true),
+            Err(error) if error.downcast_ref() == Some(&AuthError::InvalidToken) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Replaces the access token with a new one, invalidating the old one.
+    ///
+    /// This needs the client token the access token was issued to.
+    pub fn refresh(&self, auth: &mut Authentication) -> anyhow::Result<()> {
+        check_refreshable(auth)?;
+        let response = self
+            .client
+            .post(&format!("{}/refresh", self.url))
+            .json(&token_request(auth))
+            .send()?;
+        let response: Response = check_response(response)?.json()?;
+        *auth = response.into();
+        Ok(())
+    }
+
+    /// Refreshes the access token if it is no longer valid.
+    pub fn ensure_valid(&self, auth: &mut Authentication) -> anyhow::Result<()> {
+        if auth.client_token.is_none() || self.validate(auth)? {
+            return Ok(());
+        }
+        self.refresh(auth)
+    }
+
+    /// Invalidates the access token.
+    pub fn invalidate(&self, auth: &Authentication) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .post(&format!("{}/invalidate", self.url))
+            .json(&token_request(auth))
+            .send()?;
+        check_response(response)?;
+        Ok(())
+    }
+
+    /// Invalidates every access token of an account.
+    pub fn signout(&self, username: &str, password: &str) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .post(&format!("{}/signout", self.url))
+            .json(&json!({
+                "username": username,
+                "password": password,
+            }))
+            .send()?;
+        check_response(response)?;
+        Ok(())
+    }
+}
+
+impl Default for AuthService {
+    fn default() -> Self {
+        Self::new(AUTH_SERVER)
+    }
+}
+
+#[cfg(feature = "async")]
+impl AuthService<reqwest::Client> {
+    /// Whether the access token can still be used to join servers.
+    pub async fn validate(&self, auth: &Authentication) -> anyhow::Result<bool> {
+        let response = self
+            .client
+            .post(&format!("{}/validate", self.url))
+            .json(&token_request(auth))
+            .send()
+            .await?;
+        match check_response_async(response).await {
+            Ok(_) => Ok(true),
+            Err(error) if error.downcast_ref() == Some(&AuthError::InvalidToken) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Replaces the access token with a new one, invalidating the old one.
+    pub async fn refresh(&self, auth: &mut Authentication) -> anyhow::Result<()> {
+        check_refreshable(auth)?;
+        let response = self
+            .client
+            .post(&format!("{}/refresh", self.url))
+            .json(&token_request(auth))
+            .send()
+            .await?;
+        let response: Response = check_response_async(response).await?.json().await?;
+        *auth = response.into();
+        Ok(())
+    }
+
+    /// Refreshes the access token if it is no longer valid.
+    pub async fn ensure_valid(&self, auth: &mut Authentication) -> anyhow::Result<()> {
+        if auth.client_token.is_none() || self.validate(auth).await? {
+            return Ok(());
+        }
+        self.refresh(auth).await
+    }
+}
+
+/// A session server, which lets online-mode servers check that a player is who they claim to be.
+///
+/// Like `AuthService`, this has a blocking client by default, or an async one for `AsyncLogin`.
+#[derive(Debug, Clone)]
+pub struct SessionService<C = Client> {
+    pub url: String,
+    pub client: C,
+}
+
+impl<C> SessionService<C> {
+    pub fn with_client(url: &str, client: C) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            client,
+        }
+    }
+
+    fn join_url(&self) -> String {
+        format!("{}/session/minecraft/join", self.url)
+    }
+}
+
+impl SessionService {
+    pub fn new(url: &str) -> Self {
+        Self::with_client(url, Client::new())
+    }
+
+    /// Tells the session server that the player is joining the server with the given hash.
+    pub fn join(&self, auth: &Authentication, server_hash: &str) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .post(&self.join_url())
+            .json(&join_request(auth, server_hash)?)
+            .send()?;
+        check_response(response).context("failed to join the session")?;
+        Ok(())
+    }
+
+    /// Checks that the player has joined the server with the given hash, returning their
+    /// profile, or `None` if they have not.
+    pub fn has_joined(
+        &self,
+        username: &str,
+        server_hash: &str,
+    ) -> anyhow::Result<Option<GameProfile>> {
+        let response = self
+            .client
+            .get(&format!("{}/session/minecraft/hasJoined", self.url))
+            .query(&[("username", username), ("serverId", server_hash)])
+            .send()?;
+        let response = check_response(response).context("failed to verify the session")?;
+        if response.status() == StatusCode::NO_CONTENT {
+            return Ok(None);
+        }
+        Ok(Some(response.json()?))
+    }
+}
+
+impl Default for SessionService {
+    fn default() -> Self {
+        Self::new(SESSION_SERVER)
+    }
+}
+
+#[cfg(feature = "async")]
+impl SessionService<reqwest::Client> {
+    /// Tells the session server that the player is joining the server with the given hash.
+    pub async fn join(&self, auth: &Authentication, server_hash: &str) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .post(&self.join_url())
+            .json(&join_request(auth, server_hash)?)
+            .send()
+            .await?;
+        check_response_async(response)
+            .await
+            .context("failed to join the session")?;
+        Ok(())
+    }
+}
+
+/// A player's profile, as verified by the session server.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GameProfile {
+    /// The UUID, without dashes.
+    pub id: String,
+    pub name: String,
+    /// Skin and cape textures, signed by the session server.
+    #[serde(default)]
+    pub properties: Vec<ProfileProperty>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ProfileProperty {
+    pub name: String,
+    pub value: String,
+    pub signature: Option<String>,
+}
+
+/// The server ID that the client and server both send to the session server, which ties the
+/// session to the shared secret and the server's key.
+pub fn server_hash(server_id: &str, shared_secret: &[u8], public_key_der: &[u8]) -> String {
+    let mut hash = Sha1::new()
+        .chain(server_id)
+        .chain(shared_secret)
+        .chain(public_key_der)
+        .finalize();
+
+    let mut hexdigest = String::with_capacity(41);
+    // twos complement hexdigest, because why not?
+    // let's make this protocol as convoluted as possible.
+    if hash[0] & 0x80 != 0 {
+        hexdigest.push('-');
+        let mut carry = true;
+        for byte in hash.as_mut_slice().iter_mut().rev() {
+            *byte = !*byte;
+            if carry {
+                let (next_byte, next_carry) = byte.overflowing_add(1);
+                *byte = next_byte;
+                carry = next_carry;
+            }
+        }
+    }
+    let mut digits = String::with_capacity(40);
+    for byte in hash.as_slice() {
+        write!(digits, "{:02x}", byte).unwrap();
+    }
+    // Like Java's `BigInteger.toString(16)`, without leading zeros.
+    hexdigest.push_str(digits.trim_start_matches('0'));
+    hexdigest
+}
+
+/// The body of the requests that validate, refresh or invalidate an access token.
+fn token_request(auth: &Authentication) -> serde_json::Value {
+    json!({
+        "accessToken": auth.access_token,
+        "clientToken": auth.client_token,
+    })
+}
+
+fn check_refreshable(auth: &Authentication) -> anyhow::Result<()> {
+    if auth.client_token.is_none() {
+        return Err(anyhow::Error::msg(
+            "cannot refresh an access token without a client token",
+        ));
+    }
+    Ok(())
+}
+
+/// The body of the request that lets the server verify the player.
+///
+/// This fails for offline-mode players, which have no session to join with.
+fn join_request(auth: &Authentication, server_hash: &str) -> anyhow::Result<serde_json::Value> {
+    let access_token = auth.access_token().ok_or_else(|| {
+        anyhow::Error::msg(
+            "the server requested encryption, which needs an online account; \
+             offline-mode login only works with online-mode=false",
+        )
+    })?;
+    Ok(json!({
+        "accessToken": access_token,
+        "selectedProfile": auth.uuid(),
+        "serverId": server_hash,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::test_server::{route, TestServer};
+
+    #[test]
+    fn hash() {
+        assert_eq!(
+            server_hash("Notch", &[], &[]),
+            "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48"
+        );
+        assert_eq!(
+            server_hash("jeb_", &[], &[]),
+            "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"
+        );
+        assert_eq!(
+            server_hash("simon", &[], &[]),
+            "88e16a1019277b15d58faf0541e11910eb756f6"
+        );
+    }
+
+    #[test]
+    fn custom_endpoints() {
+        let server = TestServer::start(vec![
+            route(
+                "/authserver/authenticate",
+                200,
+                json!({
+                    "accessToken": "access",
+                    "clientToken": "client",
+                    "selectedProfile": {"id": "069a79f444e94726a5befca90e38aaf5", "name": "Notch"},
+                }),
+            ),
+            route("/sessionserver/session/minecraft/join", 204, json!({})),
+        ]);
+        let client = Client::builder().user_agent("test-agent").build().unwrap();
+        let auth_service = AuthService {
+            client: client.clone(),
+            ..AuthService::new(&format!("{}/authserver/", server.url()))
+        };
+        let session_service = SessionService {
+            client,
+            ..SessionService::new(&format!("{}/sessionserver", server.url()))
+        };
+
+        let auth = auth_service
+            .authenticate("notch@example.com", "hunter2")
+            .unwrap();
+        session_service.join(&auth, "-1234abcd").unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].header("user-agent"), Some("test-agent"));
+        assert_eq!(requests[1].header("user-agent"), Some("test-agent"));
+        assert_eq!(
+            requests[1].json(),
+            json!({
+                "accessToken": "access",
+                "selectedProfile": "069a79f444e94726a5befca90e38aaf5",
+                "serverId": "-1234abcd",
+            })
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn async_services() {
+        let server = TestServer::start(vec![
+            route(
+                "/validate",
+                403,
+                json!({"error": "ForbiddenOperationException", "errorMessage": "Invalid token."}),
+            ),
+            route(
+                "/refresh",
+                200,
+                json!({
+                    "accessToken": "second",
+                    "clientToken": "client",
+                    "selectedProfile": {"id": "069a79f444e94726a5befca90e38aaf5", "name": "Notch"},
+                }),
+            ),
+            route("/session/minecraft/join", 204, json!({})),
+        ]);
+        let client = reqwest::Client::new();
+        let auth_service = AuthService::with_client(server.url(), client.clone());
+        let session_service = SessionService::with_client(server.url(), client);
+
+        let mut auth = Authentication {
+            uuid: "069a79f444e94726a5befca90e38aaf5".to_string(),
+            name: "Notch".to_string(),
+            access_token: Some("first".to_string()),
+            client_token: Some("client".to_string()),
+        };
+        auth_service.ensure_valid(&mut auth).await.unwrap();
+        assert_eq!(auth.access_token(), Some("second"));
+        session_service.join(&auth, "-1234abcd").await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[1].json()["accessToken"], "first");
+        assert_eq!(requests[2].json()["accessToken"], "second");
+    }
+}
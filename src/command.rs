@@ -0,0 +1,353 @@
+use crate::proto::play::{CommandNode, CommandParser, ParserProperties};
+use std::collections::BTreeSet;
+
+/// The tree of commands the server has declared to the client.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CommandGraph {
+    nodes: Vec<CommandNode>,
+    root: usize,
+}
+
+impl CommandGraph {
+    /// Builds a graph from the contents of a `DeclareCommands` packet, checking that every node
+    /// reference is in range and that every node has the fields its type needs.
+    pub fn new(nodes: Vec<CommandNode>, root: usize) -> anyhow::Result<Self> {
+        let in_range = |index: i32| index >= 0 && (index as usize) < nodes.len();
+        if root >= nodes.len() {
+            return Err(anyhow::Error::msg("command graph root is out of range"));
+        }
+        for node in &nodes {
+            let mut references = node.children.iter().chain(&node.redirect_node);
+            if !references.all(|child| in_range(child.0)) {
+                return Err(anyhow::Error::msg("command node reference is out of range"));
+            }
+            let node_type = node.node_type();
+            if node_type != CommandNode::ROOT && node.name.is_none() {
+                return Err(anyhow::Error::msg("command node has no name"));
+            }
+            if node_type == CommandNode::ARGUMENT && node.parser.is_none() {
+                return Err(anyhow::Error::msg("argument node has no parser"));
+            }
+        }
+        Ok(Self { nodes, root })
+    }
+
+    pub fn nodes(&self) -> &[CommandNode] {
+        &self.nodes
+    }
+
+    pub fn root(&self) -> Option<&CommandNode> {
+        self.nodes.get(self.root)
+    }
+
+    /// Names of the top-level commands, such as `tp` or `gamemode`.
+    pub fn commands(&self) -> impl Iterator<Item = &str> {
+        self.children(self.root)
+            .filter_map(move |child| self.nodes[child].name.as_ref())
+            .map(|name| name.0.as_str())
+    }
+
+    /// Checks a command, with or without the leading slash, against the graph.
+    ///
+    /// Arguments are only checked as far as the client can without the server's registries, so
+    /// an accepted command may still be rejected by the server.
+    pub fn validate(&self, command: &str) -> anyhow::Result<()> {
+        let command = command.strip_prefix('/').unwrap_or(command);
+        if self.root().is_none() {
+            return Err(anyhow::Error::msg("no commands declared"));
+        }
+        if command.is_empty() {
+            return Err(anyhow::Error::msg("empty command"));
+        }
+        if self.accepts(self.root, command) {
+            Ok(())
+        } else {
+            Err(anyhow::Error::msg(format!(
+                "unknown or incomplete command: {}",
+                command
+            )))
+        }
+    }
+
+    /// Literal completions for the last word of a partial command.
+    pub fn complete(&self, partial: &str) -> Vec<String> {
+        let partial = partial.strip_prefix('/').unwrap_or(partial);
+        let mut completions = BTreeSet::new();
+        if self.root().is_some() {
+            self.complete_from(self.root, partial, &mut completions);
+        }
+        completions.into_iter().collect()
+    }
+
+    /// Children of a node, following its redirect if it has one.
+    fn children(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        let node = &self.nodes[node];
+        let target = match node.redirect_node {
+            Some(redirect) => &self.nodes[redirect.0 as usize],
+            None => node,
+        };
+        target.children.iter().map(|child| child.0 as usize)
+    }
+
+    fn accepts(&self, node: usize, input: &str) -> bool {
+        self.children(node)
+            .any(|child| match self.consume(child, input) {
+                Some("") => self.nodes[child].is_executable(),
+                Some(rest) => match rest.strip_prefix(' ') {
+                    Some(rest) => self.accepts(child, rest),
+                    None => false,
+                },
+                None => false,
+            })
+    }
+
+    fn complete_from(&self, node: usize, input: &str, completions: &mut BTreeSet<String>) {
+        for child in self.children(node) {
+            let child_node = &self.nodes[child];
+            if !input.contains(' ') {
+                match (child_node.node_type(), &child_node.name, &child_node.parser) {
+                    (CommandNode::LITERAL, Some(name), _) if name.0.starts_with(input) => {
+                        completions.insert(name.0.clone());
+                    }
+                    (CommandNode::ARGUMENT, _, Some(parser))
+                        if parser.identifier.0 .0 == "brigadier:bool" =>
+                    {
+                        for &value in &["false", "true"] {
+                            if value.starts_with(input) {
+                                completions.insert(value.to_string());
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(rest) = self
+                .consume(child, input)
+                .and_then(|rest| rest.strip_prefix(' '))
+            {
+                self.complete_from(child, rest, completions);
+            }
+        }
+    }
+
+    /// Matches the start of `input` against a node, returning the unmatched rest.
+    fn consume<'a>(&self, node: usize, input: &'a str) -> Option<&'a str> {
+        let node = &self.nodes[node];
+        match node.node_type() {
+            CommandNode::LITERAL => {
+                let name = &node.name.as_ref()?.0;
+                let (word, rest) = split_word(input);
+                if word == name {
+                    Some(rest)
+                } else {
+                    None
+                }
+            }
+            CommandNode::ARGUMENT => parse_argument(node.parser.as_ref()?, input),
+            _ => None,
+        }
+    }
+}
+
+fn parse_argument<'a>(parser: &CommandParser, input: &'a str) -> Option<&'a str> {
+    let (word, rest) = split_word(input);
+    let accepted = match &parser.properties {
+        ParserProperties::Double(bounds) => in_bounds(word.parse().ok()?, bounds.min, bounds.max),
+        ParserProperties::Float(bounds) => in_bounds(word.parse().ok()?, bounds.min, bounds.max),
+        ParserProperties::Integer(bounds) => in_bounds(word.parse().ok()?, bounds.min, bounds.max),
+        ParserProperties::Long(bounds) => in_bounds(word.parse().ok()?, bounds.min, bounds.max),
+        ParserProperties::String(behavior) => {
+            return match behavior.0 {
+                0 => split_unquoted(input),
+                1 => split_quotable(input),
+                _ => Some(""),
+            };
+        }
+        _ => match parser.identifier.0 .0.as_str() {
+            "brigadier:bool" => word == "true" || word == "false",
+            "minecraft:message" => return Some(""),
+            "minecraft:vec3" | "minecraft:block_pos" => return split_words(input, 3),
+            "minecraft:vec2" | "minecraft:column_pos" | "minecraft:rotation" => {
+                return split_words(input, 2)
+            }
+            _ => !word.is_empty(),
+        },
+    };
+    if accepted {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+fn in_bounds<T: PartialOrd>(value: T, min: Option<T>, max: Option<T>) -> bool {
+    min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+}
+
+/// Splits off the first word, keeping spaces inside quotes, brackets and braces.
+fn split_word(input: &str) -> (&str, &str) {
+    let mut depth = 0;
+    let mut quoted = false;
+    for (i, c) in input.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '[' | '{' if !quoted => depth += 1,
+            ']' | '}' if !quoted => depth -= 1,
+            ' ' if !quoted && depth <= 0 => return (&input[..i], &input[i..]),
+            _ => {}
+        }
+    }
+    (input, "")
+}
+
+fn split_words(mut input: &str, count: usize) -> Option<&str> {
+    for i in 0..count {
+        if i > 0 {
+            input = input.strip_prefix(' ')?;
+        }
+        let (word, rest) = split_word(input);
+        if word.is_empty() {
+            return None;
+        }
+        input = rest;
+    }
+    Some(input)
+}
+
+/// A brigadier unquoted string: alphanumerics and `_-.+`.
+fn split_unquoted(input: &str) -> Option<&str> {
+    let end = input
+        .find(|c: char| !(c.is_ascii_alphanumeric() || "_-.+".contains(c)))
+        .unwrap_or(input.len());
+    if end == 0 {
+        None
+    } else {
+        Some(&input[end..])
+    }
+}
+
+fn split_quotable(input: &str) -> Option<&str> {
+    let quoted = match input.chars().next() {
+        Some(quote @ '"') | Some(quote @ '\'') => quote,
+        _ => return split_unquoted(input),
+    };
+    let mut escaped = false;
+    for (i, c) in input.char_indices().skip(1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            c if c == quoted && !escaped => return Some(&input[i + 1..]),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::play::{Clientbound, ParserBounds};
+    use crate::proto::types::{Identifier, VarInt};
+    use declio::{Decode, Encode};
+
+    fn node(
+        flags: i8,
+        children: &[i32],
+        name: Option<&str>,
+        parser: Option<(&str, ParserProperties)>,
+    ) -> CommandNode {
+        CommandNode {
+            flags,
+            children: children.iter().map(|&child| VarInt(child)).collect(),
+            redirect_node: None,
+            name: name.map(|name| name.to_string().into()),
+            parser: parser.map(|(identifier, properties)| CommandParser {
+                identifier: Identifier(identifier.to_string().into()),
+                properties,
+            }),
+            suggestions_type: None,
+        }
+    }
+
+    #[test]
+    fn validate_and_complete() {
+        const LITERAL: i8 = CommandNode::LITERAL;
+        const ARGUMENT: i8 = CommandNode::ARGUMENT;
+        const EXECUTABLE: i8 = CommandNode::EXECUTABLE;
+        let mut nodes = vec![
+            node(CommandNode::ROOT, &[1, 4, 6], None, None),
+            node(LITERAL, &[2], Some("tp"), None),
+            node(
+                ARGUMENT,
+                &[3],
+                Some("targets"),
+                Some(("minecraft:entity", ParserProperties::Entity { flags: 0 })),
+            ),
+            node(
+                ARGUMENT | EXECUTABLE,
+                &[],
+                Some("location"),
+                Some(("minecraft:vec3", ParserProperties::None)),
+            ),
+            node(LITERAL, &[5], Some("time"), None),
+            node(
+                ARGUMENT | EXECUTABLE,
+                &[],
+                Some("ticks"),
+                Some((
+                    "brigadier:integer",
+                    ParserProperties::Integer(ParserBounds {
+                        min: Some(0),
+                        max: None,
+                    }),
+                )),
+            ),
+            node(
+                LITERAL | CommandNode::HAS_REDIRECT,
+                &[],
+                Some("teleport"),
+                None,
+            ),
+        ];
+        nodes[6].redirect_node = Some(VarInt(1));
+
+        let packet = Clientbound::DeclareCommands {
+            nodes,
+            root_index: VarInt(0),
+        };
+        let mut bytes = Vec::new();
+        packet.encode((), &mut bytes).unwrap();
+        let nodes = match Clientbound::decode((), &mut bytes.as_slice()).unwrap() {
+            Clientbound::DeclareCommands { nodes, .. } => nodes,
+            _ => unreachable!(),
+        };
+
+        let graph = CommandGraph::new(nodes, 0).unwrap();
+        assert!(graph.validate("/tp @e[type=cow, limit=1] ~ ~1 ~").is_ok());
+        assert!(graph.validate("teleport @e ~ ~ ~").is_ok());
+        assert!(graph.validate("teleport @e tp ~ ~ ~").is_err());
+        assert!(graph.validate("tp Steve").is_err());
+        assert!(graph.validate("time 100").is_ok());
+        assert!(graph.validate("time -1").is_err());
+        assert!(graph.validate("kill @e").is_err());
+
+        assert_eq!(graph.complete("t"), vec!["teleport", "time", "tp"]);
+        assert_eq!(graph.complete("/te"), vec!["teleport"]);
+        assert!(graph.complete("time ").is_empty());
+        assert_eq!(graph.commands().count(), 3);
+    }
+
+    #[test]
+    fn rejects_incomplete_nodes() {
+        let nodes = vec![
+            node(CommandNode::ROOT, &[1], None, None),
+            node(CommandNode::ARGUMENT, &[], Some("count"), None),
+        ];
+        assert!(CommandGraph::new(nodes, 0).is_err());
+
+        let nodes = vec![
+            node(CommandNode::ROOT, &[1], None, None),
+            node(CommandNode::LITERAL, &[], None, None),
+        ];
+        assert!(CommandGraph::new(nodes, 0).is_err());
+    }
+}
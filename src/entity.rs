@@ -0,0 +1,317 @@
+use crate::proto::play::{EntityProperty, EquipmentSlot, MetadataEntry, MetadataValue};
+use crate::proto::types::{Angle, Chat, Slot, Uuid};
+use std::collections::HashMap;
+
+// Entity type IDs from the 1.16 registry, for the entities with dedicated spawn packets.
+pub const EXPERIENCE_ORB: i32 = 24;
+pub const PAINTING: i32 = 55;
+pub const PLAYER: i32 = 106;
+
+// Metadata indices shared by all entities (or all living entities) in 1.16.
+pub const METADATA_FLAGS: u8 = 0;
+pub const METADATA_CUSTOM_NAME: u8 = 2;
+pub const METADATA_POSE: u8 = 6;
+pub const METADATA_ITEM: u8 = 7;
+pub const METADATA_HEALTH: u8 = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entity {
+    pub id: i32,
+    pub uuid: Option<Uuid>,
+    pub type_id: i32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub head_yaw: f32,
+    /// Velocity in blocks per tick.
+    pub velocity: (f64, f64, f64),
+    pub on_ground: bool,
+    pub metadata: HashMap<u8, MetadataValue>,
+    pub equipment: HashMap<EquipmentSlot, Slot>,
+    pub properties: HashMap<String, EntityProperty>,
+}
+
+impl Entity {
+    pub fn new(id: i32, uuid: Option<Uuid>, type_id: i32, x: f64, y: f64, z: f64) -> Self {
+        Self {
+            id,
+            uuid,
+            type_id,
+            x,
+            y,
+            z,
+            yaw: 0.0,
+            pitch: 0.0,
+            head_yaw: 0.0,
+            velocity: (0.0, 0.0, 0.0),
+            on_ground: false,
+            metadata: HashMap::new(),
+            equipment: HashMap::new(),
+            properties: HashMap::new(),
+        }
+    }
+
+    pub fn position(&self) -> (f64, f64, f64) {
+        (self.x, self.y, self.z)
+    }
+
+    pub fn flags(&self) -> i8 {
+        match self.metadata.get(&METADATA_FLAGS) {
+            Some(&MetadataValue::Byte(flags)) => flags,
+            _ => 0,
+        }
+    }
+
+    pub fn custom_name(&self) -> Option<&Chat> {
+        match self.metadata.get(&METADATA_CUSTOM_NAME) {
+            Some(MetadataValue::OptChat { value, .. }) => value.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn pose(&self) -> Option<i32> {
+        match self.metadata.get(&METADATA_POSE) {
+            Some(MetadataValue::Pose(pose)) => Some(pose.0),
+            _ => None,
+        }
+    }
+
+    /// Health of a living entity.
+    pub fn health(&self) -> Option<f32> {
+        match self.metadata.get(&METADATA_HEALTH) {
+            Some(&MetadataValue::Float(health)) => Some(health),
+            _ => None,
+        }
+    }
+
+    /// Item held by an item frame or dropped item entity.
+    pub fn item(&self) -> Option<&Slot> {
+        match self.metadata.get(&METADATA_ITEM) {
+            Some(MetadataValue::Slot(slot)) => Some(slot),
+            _ => None,
+        }
+    }
+
+    /// The item in the given equipment slot, if there is one.
+    pub fn equipped(&self, slot: EquipmentSlot) -> Option<&Slot> {
+        self.equipment
+            .get(&slot)
+            .filter(|item| matches!(item, Slot::Present { .. }))
+    }
+
+    /// Item IDs of the armor pieces this entity is wearing.
+    pub fn armor(&self) -> impl Iterator<Item = i32> + '_ {
+        [
+            EquipmentSlot::Helmet,
+            EquipmentSlot::Chestplate,
+            EquipmentSlot::Leggings,
+            EquipmentSlot::Boots,
+        ]
+        .iter()
+        .filter_map(move |&slot| match self.equipped(slot) {
+            Some(Slot::Present { item_id, .. }) => Some(item_id.0),
+            _ => None,
+        })
+    }
+
+    /// The final value of an attribute such as `minecraft:generic.movement_speed`, if the server
+    /// sent it.
+    pub fn attribute(&self, key: &str) -> Option<f64> {
+        self.properties.get(key).map(EntityProperty::final_value)
+    }
+
+    pub fn distance_squared(&self, (x, y, z): (f64, f64, f64)) -> f64 {
+        let (dx, dy, dz) = (self.x - x, self.y - y, self.z - z);
+        dx * dx + dy * dy + dz * dz
+    }
+}
+
+/// Registry of the entities the server has spawned for this client.
+#[derive(Debug, Clone, Default)]
+pub struct EntityTracker {
+    entities: HashMap<i32, Entity>,
+}
+
+impl EntityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: i32) -> Option<&Entity> {
+        self.entities.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.entities.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entities.clear();
+    }
+
+    pub fn spawn(&mut self, entity: Entity) {
+        self.entities.insert(entity.id, entity);
+    }
+
+    pub fn remove(&mut self, id: i32) -> Option<Entity> {
+        self.entities.remove(&id)
+    }
+
+    /// Applies a relative move, with deltas in 1/4096 block units.
+    pub fn move_relative(&mut self, id: i32, delta: (i16, i16, i16), on_ground: bool) {
+        if let Some(entity) = self.entities.get_mut(&id) {
+            entity.x += delta.0 as f64 / 4096.0;
+            entity.y += delta.1 as f64 / 4096.0;
+            entity.z += delta.2 as f64 / 4096.0;
+            entity.on_ground = on_ground;
+        }
+    }
+
+    pub fn teleport(&mut self, id: i32, x: f64, y: f64, z: f64, on_ground: bool) {
+        if let Some(entity) = self.entities.get_mut(&id) {
+            entity.x = x;
+            entity.y = y;
+            entity.z = z;
+            entity.on_ground = on_ground;
+        }
+    }
+
+    pub fn rotate(&mut self, id: i32, yaw: Angle, pitch: Angle) {
+        if let Some(entity) = self.entities.get_mut(&id) {
+            entity.yaw = yaw.degrees();
+            entity.pitch = pitch.degrees();
+        }
+    }
+
+    pub fn set_head_yaw(&mut self, id: i32, head_yaw: Angle) {
+        if let Some(entity) = self.entities.get_mut(&id) {
+            entity.head_yaw = head_yaw.degrees();
+        }
+    }
+
+    /// Sets the velocity, given in 1/8000 block per tick units.
+    pub fn set_velocity(&mut self, id: i32, velocity: (i16, i16, i16)) {
+        if let Some(entity) = self.entities.get_mut(&id) {
+            entity.velocity = (
+                velocity.0 as f64 / 8000.0,
+                velocity.1 as f64 / 8000.0,
+                velocity.2 as f64 / 8000.0,
+            );
+        }
+    }
+
+    pub fn update_metadata(&mut self, id: i32, metadata: &[MetadataEntry]) {
+        if let Some(entity) = self.entities.get_mut(&id) {
+            for entry in metadata {
+                entity.metadata.insert(entry.index, entry.value.clone());
+            }
+        }
+    }
+
+    pub fn update_equipment(&mut self, id: i32, equipment: &[(EquipmentSlot, Slot)]) {
+        if let Some(entity) = self.entities.get_mut(&id) {
+            for (slot, item) in equipment {
+                entity.equipment.insert(*slot, item.clone());
+            }
+        }
+    }
+
+    pub fn update_properties(&mut self, id: i32, properties: &[EntityProperty]) {
+        if let Some(entity) = self.entities.get_mut(&id) {
+            for property in properties {
+                entity
+                    .properties
+                    .insert(property.key.0.clone().into(), property.clone());
+            }
+        }
+    }
+
+    /// Entities within `radius` blocks of `origin`, in no particular order.
+    pub fn within(
+        &self,
+        origin: (f64, f64, f64),
+        radius: f64,
+    ) -> impl Iterator<Item = &Entity> + '_ {
+        self.entities
+            .values()
+            .filter(move |entity| entity.distance_squared(origin) <= radius * radius)
+    }
+
+    /// The closest entity within `radius` blocks of `origin` that matches `predicate`.
+    pub fn nearest<F>(&self, origin: (f64, f64, f64), radius: f64, predicate: F) -> Option<&Entity>
+    where
+        F: Fn(&Entity) -> bool,
+    {
+        self.within(origin, radius)
+            .filter(|entity| predicate(entity))
+            .min_by(|a, b| {
+                a.distance_squared(origin)
+                    .total_cmp(&b.distance_squared(origin))
+            })
+    }
+
+    pub fn nearest_of_type(
+        &self,
+        type_id: i32,
+        origin: (f64, f64, f64),
+        radius: f64,
+    ) -> Option<&Entity> {
+        self.nearest(origin, radius, |entity| entity.type_id == type_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn movement_and_queries() {
+        let mut tracker = EntityTracker::new();
+        tracker.spawn(Entity::new(1, None, PLAYER, 0.0, 64.0, 0.0));
+        tracker.spawn(Entity::new(2, None, PLAYER, 10.0, 64.0, 0.0));
+        tracker.spawn(Entity::new(3, None, EXPERIENCE_ORB, 1.0, 64.0, 0.0));
+
+        tracker.move_relative(2, (-4096 * 4, 2048, 0), true);
+        let entity = tracker.get(2).unwrap();
+        assert_eq!(entity.position(), (6.0, 64.5, 0.0));
+        assert!(entity.on_ground);
+
+        tracker.rotate(1, Angle(64), Angle(192));
+        assert_eq!(tracker.get(1).unwrap().yaw, 90.0);
+        assert_eq!(tracker.get(1).unwrap().pitch, -90.0);
+
+        let origin = (5.0, 64.0, 0.0);
+        assert_eq!(tracker.nearest_of_type(PLAYER, origin, 8.0).unwrap().id, 2);
+        assert_eq!(tracker.nearest_of_type(PLAYER, origin, 0.5), None);
+        assert_eq!(
+            tracker
+                .nearest_of_type(EXPERIENCE_ORB, origin, 8.0)
+                .unwrap()
+                .id,
+            3
+        );
+
+        tracker.remove(2);
+        assert_eq!(tracker.nearest_of_type(PLAYER, origin, 8.0).unwrap().id, 1);
+
+        // An entity at NaN coordinates is out of range of every query.
+        tracker.spawn(Entity::new(4, None, PLAYER, f64::NAN, 64.0, 0.0));
+        assert_eq!(
+            tracker
+                .nearest_of_type(PLAYER, origin, f64::INFINITY)
+                .unwrap()
+                .id,
+            1
+        );
+    }
+}
@@ -0,0 +1,618 @@
+use crate::proto::play::Serverbound;
+use crate::proto::types::{Chat, Slot};
+use std::collections::HashMap;
+
+pub const PLAYER_WINDOW: u8 = 0;
+/// Window ID used by `SetSlot` to set the item held by the cursor.
+pub const CURSOR_WINDOW: u8 = 0xff;
+/// Window ID used by `SetSlot` to set a slot of the player inventory while a container is open.
+/// Its slots are numbered like the server's `PlayerInventory`, with the hotbar first.
+pub const PLAYER_INVENTORY_WINDOW: u8 = 0xfe;
+
+// Slot layout of the player inventory window.
+pub const PLAYER_SLOTS: usize = 46;
+pub const MAIN_INVENTORY_START: i16 = 9;
+pub const HOTBAR_START: i16 = 36;
+pub const OFFHAND_SLOT: i16 = 45;
+/// Main inventory and hotbar slots, which appear at the end of every container window.
+const PLAYER_SECTION_LEN: usize = 36;
+
+/// Slot number for clicks outside of the window.
+pub const OUTSIDE: i16 = -999;
+
+// Click modes of `ClickWindow`.
+pub const MODE_CLICK: i32 = 0;
+pub const MODE_SHIFT_CLICK: i32 = 1;
+pub const MODE_SWAP: i32 = 2;
+pub const MODE_DROP: i32 = 4;
+
+/// The client has no item registry, so every item is assumed to stack up to this size.
+const MAX_STACK_SIZE: i8 = 64;
+
+/// The player inventory or an open container.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    id: u8,
+    window_type: Option<i32>,
+    title: Option<Chat>,
+    slots: Vec<Slot>,
+    properties: HashMap<i16, i16>,
+    next_action: i16,
+}
+
+impl Window {
+    fn new(id: u8, window_type: Option<i32>, title: Option<Chat>, len: usize) -> Self {
+        Self {
+            id,
+            window_type,
+            title,
+            slots: vec![Slot::NotPresent; len],
+            properties: HashMap::new(),
+            next_action: 1,
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// The window type from `OpenWindow`, or `None` for the player inventory.
+    pub fn window_type(&self) -> Option<i32> {
+        self.window_type
+    }
+
+    pub fn title(&self) -> Option<&Chat> {
+        self.title.as_ref()
+    }
+
+    pub fn slots(&self) -> &[Slot] {
+        &self.slots
+    }
+
+    pub fn slot(&self, slot: i16) -> Option<&Slot> {
+        self.slots.get(slot as usize)
+    }
+
+    pub fn property(&self, property: i16) -> Option<i16> {
+        self.properties.get(&property).copied()
+    }
+
+    /// Index of the first main inventory slot; the main inventory and hotbar follow the
+    /// container's own slots.
+    pub fn player_offset(&self) -> usize {
+        if self.id == PLAYER_WINDOW {
+            // Followed by the offhand slot.
+            MAIN_INVENTORY_START as usize
+        } else {
+            self.slots.len().saturating_sub(PLAYER_SECTION_LEN)
+        }
+    }
+
+    /// Window slot of a hotbar slot, numbered 0 to 8.
+    pub fn hotbar_slot(&self, hotbar: u8) -> i16 {
+        (self.player_offset() + 27 + hotbar as usize) as i16
+    }
+
+    fn set(&mut self, slot: i16, item: Slot) {
+        if let Some(entry) = self.slots.get_mut(slot as usize) {
+            *entry = item;
+        }
+    }
+
+    /// Target slots of a shift-click, in the order they are filled.
+    fn shift_click_targets(&self, slot: usize) -> Vec<usize> {
+        let offset = self.player_offset();
+        let end = offset + PLAYER_SECTION_LEN;
+        if self.id == PLAYER_WINDOW {
+            let hotbar = HOTBAR_START as usize;
+            match slot {
+                _ if (offset..hotbar).contains(&slot) => (hotbar..end).collect(),
+                _ if (hotbar..end).contains(&slot) => (offset..hotbar).collect(),
+                _ => (offset..end).collect(),
+            }
+        } else if slot < offset {
+            (offset..end).rev().collect()
+        } else {
+            (0..offset).collect()
+        }
+    }
+}
+
+struct PendingClick {
+    window_id: u8,
+    action_number: i16,
+    slots: Vec<Slot>,
+    cursor: Slot,
+}
+
+/// Client-side model of the player inventory and the currently open container.
+///
+/// Clicks are applied locally as soon as they are sent, and rolled back if the server rejects
+/// them.
+pub struct Inventory {
+    player: Window,
+    open: Option<Window>,
+    cursor: Slot,
+    pending: Vec<PendingClick>,
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self {
+            player: Window::new(PLAYER_WINDOW, None, None, PLAYER_SLOTS),
+            open: None,
+            cursor: Slot::NotPresent,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn player(&self) -> &Window {
+        &self.player
+    }
+
+    pub fn open_window(&self) -> Option<&Window> {
+        self.open.as_ref()
+    }
+
+    /// The open container, or the player inventory if no container is open.
+    pub fn current(&self) -> &Window {
+        self.open.as_ref().unwrap_or(&self.player)
+    }
+
+    pub fn cursor(&self) -> &Slot {
+        &self.cursor
+    }
+
+    /// The item in a hotbar slot, numbered 0 to 8.
+    pub fn hotbar(&self, hotbar: u8) -> &Slot {
+        &self.player.slots[HOTBAR_START as usize + hotbar as usize]
+    }
+
+    fn current_mut(&mut self) -> &mut Window {
+        self.open.as_mut().unwrap_or(&mut self.player)
+    }
+
+    fn window_mut(&mut self, window_id: u8) -> Option<&mut Window> {
+        if window_id == PLAYER_WINDOW {
+            Some(&mut self.player)
+        } else {
+            self.open.as_mut().filter(|window| window.id == window_id)
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Applies an `OpenWindow` packet. The slots are filled by the following `WindowItems`.
+    pub fn open(&mut self, window_id: u8, window_type: i32, title: Chat) {
+        self.open = Some(Window::new(window_id, Some(window_type), Some(title), 0));
+        self.pending.clear();
+    }
+
+    /// Closes the open container, as requested by the server or by the client.
+    pub fn close(&mut self) {
+        self.open = None;
+        self.cursor = Slot::NotPresent;
+        self.pending.clear();
+    }
+
+    /// Closes the open container, returning the packet to send to the server.
+    pub fn close_window(&mut self) -> Serverbound {
+        let window_id = self.open.as_ref().map_or(PLAYER_WINDOW, |window| window.id);
+        self.close();
+        Serverbound::CloseWindow { window_id }
+    }
+
+    pub fn set_window_items(&mut self, window_id: u8, items: &[Slot]) {
+        if let Some(window) = self.window_mut(window_id) {
+            window.slots = items.to_vec();
+        }
+        if window_id == PLAYER_WINDOW {
+            self.player.slots.resize(PLAYER_SLOTS, Slot::NotPresent);
+            self.sync_from_player();
+        } else {
+            self.sync_to_player();
+        }
+    }
+
+    pub fn set_slot(&mut self, window_id: u8, slot: i16, item: Slot) {
+        match window_id {
+            CURSOR_WINDOW => self.cursor = item,
+            PLAYER_WINDOW => {
+                self.player.set(slot, item);
+                self.sync_from_player();
+            }
+            PLAYER_INVENTORY_WINDOW => {
+                if let Some(slot) = player_inventory_slot(slot) {
+                    self.player.set(slot, item);
+                    self.sync_from_player();
+                }
+            }
+            _ => {
+                if let Some(window) = self.window_mut(window_id) {
+                    window.set(slot, item);
+                    self.sync_to_player();
+                }
+            }
+        }
+    }
+
+    pub fn set_property(&mut self, window_id: u8, property: i16, value: i16) {
+        if let Some(window) = self.window_mut(window_id) {
+            window.properties.insert(property, value);
+        }
+    }
+
+    /// Handles a `WindowConfirmation`, returning the acknowledgement to send if the server
+    /// rejected a click.
+    ///
+    /// A rejected click and every click sent after it are rolled back; the server follows the
+    /// rejection with the actual window contents.
+    pub fn confirm(
+        &mut self,
+        window_id: u8,
+        action_number: i16,
+        accepted: bool,
+    ) -> Option<Serverbound> {
+        let index = self
+            .pending
+            .iter()
+            .position(|click| click.window_id == window_id && click.action_number == action_number);
+        if accepted {
+            if let Some(index) = index {
+                self.pending.remove(index);
+            }
+            return None;
+        }
+
+        if let Some(index) = index {
+            let click = self.pending.remove(index);
+            self.pending.retain(|other| other.window_id != window_id);
+            self.cursor = click.cursor;
+            if let Some(window) = self.window_mut(window_id) {
+                window.slots = click.slots;
+            }
+            if window_id == PLAYER_WINDOW {
+                self.sync_from_player();
+            } else {
+                self.sync_to_player();
+            }
+        }
+        Some(Serverbound::WindowConfirmation {
+            window_id,
+            action_number,
+            accepted: false,
+        })
+    }
+
+    /// Performs a raw click in the current window, returning the packet to send.
+    pub fn click(&mut self, slot: i16, button: i8, mode: i32) -> Serverbound {
+        let window = self.current_mut();
+        let window_id = window.id;
+        let action_number = window.next_action;
+        window.next_action = window.next_action.wrapping_add(1);
+        // The server expects no item for hotbar swaps and drops, and rejects them otherwise.
+        let clicked = match mode {
+            MODE_SWAP | MODE_DROP => Slot::NotPresent,
+            _ => window.slot(slot).cloned().unwrap_or(Slot::NotPresent),
+        };
+
+        self.pending.push(PendingClick {
+            window_id,
+            action_number,
+            slots: self.current().slots.clone(),
+            cursor: self.cursor.clone(),
+        });
+        self.apply_click(slot, button, mode);
+        if self.open.is_some() {
+            self.sync_to_player();
+        }
+
+        Serverbound::ClickWindow {
+            window_id,
+            slot,
+            button,
+            action_number,
+            mode: mode.into(),
+            clicked,
+        }
+    }
+
+    /// Moves the stack in one slot of the current window to another, swapping them if the
+    /// target holds a different item.
+    pub fn move_stack(&mut self, from: i16, to: i16) -> Vec<Serverbound> {
+        let mut packets = vec![
+            self.click(from, 0, MODE_CLICK),
+            self.click(to, 0, MODE_CLICK),
+        ];
+        if let Slot::Present { .. } = self.cursor {
+            packets.push(self.click(from, 0, MODE_CLICK));
+        }
+        packets
+    }
+
+    pub fn shift_click(&mut self, slot: i16) -> Serverbound {
+        self.click(slot, 0, MODE_SHIFT_CLICK)
+    }
+
+    /// Drops one item from a slot of the current window, or the whole stack.
+    pub fn drop_item(&mut self, slot: i16, whole_stack: bool) -> Serverbound {
+        self.click(slot, whole_stack as i8, MODE_DROP)
+    }
+
+    /// Swaps a slot of the current window with a hotbar slot, numbered 0 to 8.
+    pub fn swap_with_hotbar(&mut self, slot: i16, hotbar: u8) -> Serverbound {
+        self.click(slot, hotbar as i8, MODE_SWAP)
+    }
+
+    fn apply_click(&mut self, slot: i16, button: i8, mode: i32) {
+        let cursor = &mut self.cursor;
+        let window = self.open.as_mut().unwrap_or(&mut self.player);
+        if slot == OUTSIDE {
+            if mode == MODE_CLICK {
+                let dropped = if button == 0 { count(cursor) } else { 1 };
+                *cursor = with_count(cursor, count(cursor) - dropped);
+            }
+            return;
+        }
+        let index = slot as usize;
+        let item = match window.slots.get(index) {
+            Some(item) => item.clone(),
+            None => return,
+        };
+
+        match mode {
+            MODE_CLICK if button == 0 => {
+                if is_empty(cursor) || is_empty(&item) || !same_item(cursor, &item) {
+                    window.slots[index] = std::mem::replace(cursor, item);
+                } else {
+                    let moved = count(cursor).min(MAX_STACK_SIZE - count(&item));
+                    window.slots[index] = with_count(&item, count(&item) + moved);
+                    *cursor = with_count(cursor, count(cursor) - moved);
+                }
+            }
+            MODE_CLICK => {
+                if is_empty(cursor) {
+                    let taken = (count(&item) + 1) / 2;
+                    *cursor = with_count(&item, taken);
+                    window.slots[index] = with_count(&item, count(&item) - taken);
+                } else if is_empty(&item) {
+                    window.slots[index] = with_count(cursor, 1);
+                    *cursor = with_count(cursor, count(cursor) - 1);
+                } else if same_item(cursor, &item) {
+                    if count(&item) < MAX_STACK_SIZE {
+                        window.slots[index] = with_count(&item, count(&item) + 1);
+                        *cursor = with_count(cursor, count(cursor) - 1);
+                    }
+                } else {
+                    window.slots[index] = std::mem::replace(cursor, item);
+                }
+            }
+            MODE_SHIFT_CLICK => {
+                let mut remaining = item;
+                window.slots[index] = Slot::NotPresent;
+                let targets = window.shift_click_targets(index);
+                for &target in &targets {
+                    let existing = &window.slots[target];
+                    if same_item(existing, &remaining) && count(existing) < MAX_STACK_SIZE {
+                        let moved = count(&remaining).min(MAX_STACK_SIZE - count(existing));
+                        window.slots[target] = with_count(existing, count(existing) + moved);
+                        remaining = with_count(&remaining, count(&remaining) - moved);
+                    }
+                }
+                if let Some(&target) = targets
+                    .iter()
+                    .find(|&&target| is_empty(&window.slots[target]))
+                {
+                    window.slots[target] = std::mem::replace(&mut remaining, Slot::NotPresent);
+                }
+                window.slots[index] = remaining;
+            }
+            MODE_SWAP => {
+                let hotbar = window.hotbar_slot(button as u8) as usize;
+                if hotbar < window.slots.len() {
+                    window.slots.swap(index, hotbar);
+                }
+            }
+            MODE_DROP => {
+                let dropped = if button == 0 { 1 } else { count(&item) };
+                window.slots[index] = with_count(&item, count(&item) - dropped);
+            }
+            _ => {}
+        }
+    }
+
+    /// Copies the player section of the open container into the player inventory.
+    fn sync_to_player(&mut self) {
+        if let Some(window) = &self.open {
+            let offset = window.player_offset();
+            let start = MAIN_INVENTORY_START as usize;
+            for (i, item) in window.slots[offset..].iter().enumerate() {
+                self.player.slots[start + i] = item.clone();
+            }
+        }
+    }
+
+    /// Copies the player inventory into the player section of the open container.
+    fn sync_from_player(&mut self) {
+        if let Some(window) = &mut self.open {
+            let offset = window.player_offset();
+            let start = MAIN_INVENTORY_START as usize;
+            let len = window.slots.len() - offset;
+            window.slots[offset..].clone_from_slice(&self.player.slots[start..start + len]);
+        }
+    }
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_empty(slot: &Slot) -> bool {
+    count(slot) == 0
+}
+
+fn count(slot: &Slot) -> i8 {
+    match slot {
+        Slot::Present { item_count, .. } => *item_count,
+        Slot::NotPresent => 0,
+    }
+}
+
+fn with_count(slot: &Slot, count: i8) -> Slot {
+    match slot {
+        Slot::Present { item_id, nbt, .. } if count > 0 => Slot::Present {
+            item_id: *item_id,
+            item_count: count,
+            nbt: nbt.clone(),
+        },
+        _ => Slot::NotPresent,
+    }
+}
+
+fn same_item(a: &Slot, b: &Slot) -> bool {
+    match (a, b) {
+        (
+            Slot::Present {
+                item_id: a_id,
+                nbt: a_nbt,
+                ..
+            },
+            Slot::Present {
+                item_id: b_id,
+                nbt: b_nbt,
+                ..
+            },
+        ) => a_id.0 == b_id.0 && a_nbt == b_nbt,
+        _ => false,
+    }
+}
+
+/// Converts a slot index of the server's `PlayerInventory`, as used by `SetSlot` with
+/// `PLAYER_INVENTORY_WINDOW`, into a slot of the player window.
+fn player_inventory_slot(slot: i16) -> Option<i16> {
+    match slot {
+        0..=8 => Some(HOTBAR_START + slot),
+        9..=35 => Some(slot),
+        // Armor is stored from feet to head, but shown from head to feet.
+        36..=39 => Some(44 - slot),
+        40 => Some(OFFHAND_SLOT),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::item;
+
+    #[test]
+    fn clicks_and_rejection() {
+        let mut inventory = Inventory::new();
+        inventory.set_slot(PLAYER_WINDOW, 9, item(1, 40));
+        inventory.set_slot(PLAYER_WINDOW, 10, item(1, 40));
+        inventory.set_slot(PLAYER_WINDOW, 36, item(2, 1));
+
+        let packets = inventory.move_stack(9, 10);
+        assert_eq!(packets.len(), 3);
+        match &packets[0] {
+            Serverbound::ClickWindow {
+                window_id,
+                slot,
+                action_number,
+                clicked,
+                ..
+            } => {
+                assert_eq!((*window_id, *slot, *action_number), (0, 9, 1));
+                assert_eq!(clicked, &item(1, 40));
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(inventory.player().slot(9), Some(&item(1, 16)));
+        assert_eq!(inventory.player().slot(10), Some(&item(1, 64)));
+        assert_eq!(inventory.cursor(), &Slot::NotPresent);
+
+        inventory.confirm(PLAYER_WINDOW, 1, true);
+        inventory.confirm(PLAYER_WINDOW, 2, true);
+        inventory.confirm(PLAYER_WINDOW, 3, true);
+
+        inventory.swap_with_hotbar(9, 0);
+        assert_eq!(inventory.hotbar(0), &item(1, 16));
+        assert_eq!(inventory.player().slot(9), Some(&item(2, 1)));
+
+        // The swap is rejected, so it is undone and acknowledged.
+        let ack = inventory.confirm(PLAYER_WINDOW, 4, false);
+        assert_eq!(
+            ack,
+            Some(Serverbound::WindowConfirmation {
+                window_id: 0,
+                action_number: 4,
+                accepted: false,
+            })
+        );
+        assert_eq!(inventory.hotbar(0), &item(2, 1));
+        assert_eq!(inventory.player().slot(9), Some(&item(1, 16)));
+    }
+
+    #[test]
+    fn clicked_item() {
+        let clicked = |packet: Serverbound| match packet {
+            Serverbound::ClickWindow { clicked, .. } => clicked,
+            _ => unreachable!(),
+        };
+        let mut inventory = Inventory::new();
+        for slot in 9..12 {
+            inventory.set_slot(PLAYER_WINDOW, slot, item(1, 40));
+        }
+
+        assert_eq!(clicked(inventory.shift_click(9)), item(1, 40));
+        assert_eq!(clicked(inventory.drop_item(10, true)), Slot::NotPresent);
+        assert_eq!(clicked(inventory.swap_with_hotbar(11, 0)), Slot::NotPresent);
+    }
+
+    #[test]
+    fn player_inventory_indices() {
+        let mut inventory = Inventory::new();
+        inventory.set_slot(PLAYER_INVENTORY_WINDOW, 0, item(5, 1));
+        assert_eq!(inventory.player().slot(36), Some(&item(5, 1)));
+        assert_eq!(inventory.hotbar(0), &item(5, 1));
+        assert_eq!(inventory.player().slot(0), Some(&Slot::NotPresent));
+
+        inventory.set_slot(PLAYER_INVENTORY_WINDOW, 39, item(6, 1));
+        assert_eq!(inventory.player().slot(5), Some(&item(6, 1)));
+        inventory.set_slot(PLAYER_INVENTORY_WINDOW, 40, item(7, 1));
+        assert_eq!(inventory.player().slot(OFFHAND_SLOT), Some(&item(7, 1)));
+    }
+
+    #[test]
+    fn container_window() {
+        let mut inventory = Inventory::new();
+        inventory.set_slot(PLAYER_WINDOW, 36, item(3, 5));
+        inventory.open(1, 2, Chat(serde_json::json!("Chest")));
+
+        let mut items = vec![Slot::NotPresent; 27 + 36];
+        items[0] = item(4, 64);
+        items[27 + 27] = item(3, 5);
+        inventory.set_window_items(1, &items);
+
+        // Shift-clicking a container slot fills the player section from the end.
+        inventory.shift_click(0);
+        let window = inventory.open_window().unwrap();
+        assert_eq!(window.slot(0), Some(&Slot::NotPresent));
+        assert_eq!(window.slot(62), Some(&item(4, 64)));
+        assert_eq!(inventory.player().slot(44), Some(&item(4, 64)));
+
+        inventory.drop_item(window.hotbar_slot(0), false);
+        assert_eq!(inventory.hotbar(0), &item(3, 4));
+
+        assert_eq!(
+            inventory.close_window(),
+            Serverbound::CloseWindow { window_id: 1 }
+        );
+        assert!(inventory.open_window().is_none());
+        assert_eq!(inventory.current().id(), PLAYER_WINDOW);
+    }
+}
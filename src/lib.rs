@@ -1,8 +1,18 @@
 #[macro_use]
 mod macros;
+#[cfg(test)]
+mod test_util;
 mod util;
 
+pub mod advancement;
 pub mod auth;
+pub mod command;
+pub mod entity;
+pub mod inventory;
 pub mod nbt;
+pub mod pathfind;
+pub mod physics;
 pub mod proto;
+pub mod recipe;
 pub mod state;
+pub mod world;
@@ -9,7 +9,8 @@ use std::io;
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
 pub struct Nbt {
     tag: Tag,
-    #[declio(with = "string")]
+    // An empty NBT structure is just the end tag, without a name.
+    #[declio(skip_if = "*tag == Tag::End", with = "string")]
     name: String,
     #[declio(ctx(decode = "*tag"))]
     value: Value,
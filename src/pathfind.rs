@@ -0,0 +1,476 @@
+use crate::physics::{MovementInput, PlayerPhysics};
+use crate::proto::types::Position;
+use crate::world::block::BlockShape;
+use crate::world::World;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::time::{Duration, Instant};
+
+/// How the player gets from one node of a path to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Movement {
+    /// The first node of a path.
+    Start,
+    Walk,
+    /// Jump onto a block one higher.
+    JumpUp,
+    /// Walk off a ledge and fall.
+    Drop,
+    Swim,
+    /// Climb up or down a ladder or vine.
+    Climb,
+}
+
+/// Costs of each kind of movement, per move from one node to the next.
+///
+/// A jump moves one block up and one across, and a drop costs `drop` per block fallen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathCosts {
+    pub walk: f64,
+    pub jump_up: f64,
+    pub drop: f64,
+    pub swim: f64,
+    pub climb: f64,
+}
+
+impl Default for PathCosts {
+    fn default() -> Self {
+        Self {
+            walk: 1.0,
+            jump_up: 2.0,
+            drop: 1.5,
+            swim: 3.0,
+            climb: 1.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathConfig {
+    pub costs: PathCosts,
+    /// Nodes further than this from the start, in blocks, are not explored.
+    pub max_distance: f64,
+    /// The search gives up after this long.
+    pub timeout: Duration,
+    /// The highest ledge the path may drop down from.
+    pub max_drop: i32,
+}
+
+impl Default for PathConfig {
+    fn default() -> Self {
+        Self {
+            costs: PathCosts::default(),
+            max_distance: 128.0,
+            timeout: Duration::from_millis(500),
+            max_drop: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathNode {
+    /// The block the player's feet are in.
+    pub position: Position,
+    /// How this node is reached from the previous one.
+    pub movement: Movement,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    pub nodes: Vec<PathNode>,
+    pub cost: f64,
+}
+
+/// Finds the cheapest path for the player's feet from `start` to `goal` with A*.
+pub fn find_path(
+    world: &World,
+    start: Position,
+    goal: Position,
+    config: &PathConfig,
+) -> anyhow::Result<Path> {
+    let started = Instant::now();
+    if !is_standable(world, goal) {
+        return Err(anyhow::Error::msg("goal is not a standable position"));
+    }
+    let min_cost = min_cost_per_block(&config.costs);
+    let heuristic = |position: Position| distance(position, goal) * min_cost;
+
+    let mut open = BinaryHeap::new();
+    let mut best: HashMap<Position, (f64, Option<Position>, Movement)> = HashMap::new();
+    open.push(OpenNode {
+        position: start,
+        cost: 0.0,
+        estimate: heuristic(start),
+    });
+    best.insert(start, (0.0, None, Movement::Start));
+
+    while let Some(OpenNode { position, cost, .. }) = open.pop() {
+        if position == goal {
+            return Ok(reconstruct(&best, goal, cost));
+        }
+        if cost > best[&position].0 {
+            continue;
+        }
+        if started.elapsed() > config.timeout {
+            return Err(anyhow::Error::msg("pathfinding timed out"));
+        }
+
+        for (next, movement, step_cost) in neighbors(world, position, config) {
+            if distance(start, next) > config.max_distance {
+                continue;
+            }
+            let next_cost = cost + step_cost;
+            if best
+                .get(&next)
+                .is_none_or(|&(known, _, _)| next_cost < known)
+            {
+                best.insert(next, (next_cost, Some(position), movement));
+                open.push(OpenNode {
+                    position: next,
+                    cost: next_cost,
+                    estimate: next_cost + heuristic(next),
+                });
+            }
+        }
+    }
+    Err(anyhow::Error::msg("no path found"))
+}
+
+fn reconstruct(
+    best: &HashMap<Position, (f64, Option<Position>, Movement)>,
+    goal: Position,
+    cost: f64,
+) -> Path {
+    let mut nodes = Vec::new();
+    let mut current = Some(goal);
+    while let Some(position) = current {
+        let (_, previous, movement) = best[&position];
+        nodes.push(PathNode { position, movement });
+        current = previous;
+    }
+    nodes.reverse();
+    Path { nodes, cost }
+}
+
+struct OpenNode {
+    position: Position,
+    cost: f64,
+    estimate: f64,
+}
+
+impl PartialEq for OpenNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for OpenNode {}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenNode {
+    /// Reversed, so that the heap pops the lowest estimate first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .partial_cmp(&self.estimate)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// The lowest cost of any move divided by the distance it covers, so that the A* heuristic
+/// never overestimates.
+///
+/// Jumps and one-block drops cover √2 blocks; longer drops cost more per block than those.
+fn min_cost_per_block(costs: &PathCosts) -> f64 {
+    costs
+        .walk
+        .min(costs.jump_up / 2f64.sqrt())
+        .min(costs.drop / 2f64.sqrt())
+        .min(costs.swim)
+        .min(costs.climb)
+}
+
+const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+fn neighbors(world: &World, from: Position, config: &PathConfig) -> Vec<(Position, Movement, f64)> {
+    let costs = &config.costs;
+    let mut result = Vec::new();
+    let shape = |position: Position| world.shape_at(position);
+
+    if shape(from).is_climbable() {
+        let up = offset(from, 0, 1, 0);
+        if is_passable(shape(up)) && is_passable(shape(offset(up, 0, 1, 0))) {
+            result.push((up, Movement::Climb, costs.climb));
+        }
+        let down = offset(from, 0, -1, 0);
+        if shape(down).is_climbable() || is_standable(world, down) {
+            result.push((down, Movement::Climb, costs.climb));
+        }
+    }
+    if shape(from) == BlockShape::Water {
+        for &(dx, dy, dz) in &[(0, 1, 0), (0, -1, 0)] {
+            let next = offset(from, dx, dy, dz);
+            if shape(next) == BlockShape::Water && is_passable(shape(offset(next, 0, 1, 0))) {
+                result.push((next, Movement::Swim, costs.swim));
+            }
+        }
+    }
+
+    for &(dx, dz) in &DIRECTIONS {
+        let next = offset(from, dx, 0, dz);
+        if is_standable(world, next) {
+            let movement = if shape(next) == BlockShape::Water || shape(from) == BlockShape::Water {
+                (Movement::Swim, costs.swim)
+            } else {
+                (Movement::Walk, costs.walk)
+            };
+            result.push((next, movement.0, movement.1));
+            continue;
+        }
+
+        let above = offset(next, 0, 1, 0);
+        if is_standable(world, above) && is_passable(shape(offset(from, 0, 2, 0))) {
+            result.push((above, Movement::JumpUp, costs.jump_up));
+            continue;
+        }
+
+        if is_passable(shape(next)) && is_passable(shape(above)) {
+            for fall in 1..=config.max_drop {
+                let below = offset(next, 0, -fall, 0);
+                if is_standable(world, below) {
+                    result.push((below, Movement::Drop, costs.drop * fall as f64));
+                    break;
+                }
+                if !is_passable(shape(below)) || shape(below) == BlockShape::Water {
+                    break;
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Whether the player can be at a position: room for the body and something to stand on,
+/// swim in or hold on to. Blocks taller than one block, like fences, cannot be stood on.
+fn is_standable(world: &World, position: Position) -> bool {
+    let feet = world.shape_at(position);
+    let head = world.shape_at(offset(position, 0, 1, 0));
+    let ground = world.shape_at(offset(position, 0, -1, 0));
+    is_passable(feet)
+        && is_passable(head)
+        && (ground
+            .collision_box()
+            .is_some_and(|ground| ground.max.1 <= 1.0)
+            || feet == BlockShape::Water
+            || feet.is_climbable())
+}
+
+fn is_passable(shape: BlockShape) -> bool {
+    match shape {
+        BlockShape::Empty | BlockShape::Water | BlockShape::Climbable(_) => true,
+        BlockShape::Full | BlockShape::Partial(_) | BlockShape::Lava => false,
+    }
+}
+
+fn offset(position: Position, dx: i32, dy: i32, dz: i32) -> Position {
+    Position {
+        x: position.x + dx,
+        y: position.y + dy,
+        z: position.z + dz,
+    }
+}
+
+fn distance(a: Position, b: Position) -> f64 {
+    let (dx, dy, dz) = ((a.x - b.x) as f64, (a.y - b.y) as f64, (a.z - b.z) as f64);
+    (dx * dx + dy * dy + dz * dz).sqrt()
+}
+
+/// Steers the player along a path, one node at a time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathFollower {
+    path: Path,
+    next: usize,
+}
+
+impl PathFollower {
+    pub fn new(path: Path) -> Self {
+        Self { path, next: 1 }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The node the player is currently heading to.
+    pub fn next_node(&self) -> Option<&PathNode> {
+        self.path.nodes.get(self.next)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.path.nodes.len()
+    }
+
+    /// Picks the input and yaw for the next tick, advancing to the next node once the player
+    /// has reached the current one.
+    pub fn steer(&mut self, physics: &PlayerPhysics) -> Option<(MovementInput, f32)> {
+        let (x, y, z) = physics.position();
+        while let Some(node) = self.next_node() {
+            let (dx, dz) = center_offset(node.position, x, z);
+            let dy = node.position.y as f64 - y;
+            if dx * dx + dz * dz < 0.35 * 0.35 && dy.abs() < 0.6 {
+                self.next += 1;
+            } else {
+                break;
+            }
+        }
+        let node = *self.next_node()?;
+
+        let (dx, dz) = center_offset(node.position, x, z);
+        let yaw = (-dx).atan2(dz).to_degrees() as f32;
+        let horizontal = (dx * dx + dz * dz).sqrt();
+        let rising = node.position.y as f64 > y + 0.1;
+        let input = MovementInput {
+            forward: if horizontal > 0.1 { 1.0 } else { 0.0 },
+            jump: rising
+                && matches!(
+                    node.movement,
+                    Movement::JumpUp | Movement::Swim | Movement::Climb
+                ),
+            ..MovementInput::default()
+        };
+        Some((input, yaw))
+    }
+}
+
+fn center_offset(position: Position, x: f64, z: f64) -> (f64, f64) {
+    (position.x as f64 + 0.5 - x, position.z as f64 + 0.5 - z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::test_world as world;
+
+    const STONE: i32 = 1;
+    /// A ladder facing south.
+    const LADDER: i32 = 3640;
+
+    fn position(x: i32, y: i32, z: i32) -> Position {
+        Position { x, y, z }
+    }
+
+    #[test]
+    fn walks_around_and_over_walls() {
+        // A wall at x = 5 with a one block high gap at z = 8.
+        let mut blocks = Vec::new();
+        for z in 0..16 {
+            blocks.push((5, 64, z, STONE));
+            if z != 8 {
+                blocks.push((5, 65, z, STONE));
+            }
+        }
+        let world = world(&blocks);
+        let config = PathConfig::default();
+
+        let path = find_path(&world, position(2, 64, 2), position(8, 64, 2), &config).unwrap();
+        let movements: Vec<_> = path.nodes.iter().map(|node| node.movement).collect();
+        assert!(path
+            .nodes
+            .iter()
+            .any(|node| node.position == position(5, 65, 8)));
+        assert!(movements.contains(&Movement::JumpUp));
+        assert!(movements.contains(&Movement::Drop));
+        assert_eq!(path.nodes.last().unwrap().position, position(8, 64, 2));
+
+        let far = PathConfig {
+            max_distance: 4.0,
+            ..PathConfig::default()
+        };
+        assert!(find_path(&world, position(2, 64, 2), position(8, 64, 2), &far).is_err());
+    }
+
+    #[test]
+    fn climbs_ladders() {
+        // A pillar with a ladder on its side up to a platform at y = 68.
+        let mut blocks = vec![(4, 68, 4, STONE)];
+        for y in 64..68 {
+            blocks.push((4, y, 4, STONE));
+            blocks.push((4, y, 5, LADDER));
+        }
+        blocks.push((4, 68, 5, LADDER));
+        let world = world(&blocks);
+
+        let path = find_path(
+            &world,
+            position(4, 64, 5),
+            position(4, 69, 4),
+            &PathConfig::default(),
+        )
+        .unwrap();
+        assert!(path
+            .nodes
+            .iter()
+            .any(|node| node.movement == Movement::Climb));
+    }
+
+    #[test]
+    fn heuristic_is_admissible() {
+        // Steps up to a ledge at y = 66, with a drop off its far side.
+        let world = world(&[(5, 64, 4, STONE), (6, 64, 4, STONE), (6, 65, 4, STONE)]);
+        let config = PathConfig {
+            costs: PathCosts {
+                walk: 1.0,
+                jump_up: 1.0,
+                drop: 1.0,
+                swim: 1.0,
+                climb: 1.0,
+            },
+            ..PathConfig::default()
+        };
+        let min_cost = min_cost_per_block(&config.costs);
+        let mut movements = Vec::new();
+        for &from in &[position(4, 64, 4), position(5, 65, 4), position(6, 66, 4)] {
+            for (next, movement, cost) in neighbors(&world, from, &config) {
+                assert!(cost >= distance(from, next) * min_cost);
+                movements.push(movement);
+            }
+        }
+        assert!(movements.contains(&Movement::JumpUp));
+        assert!(movements.contains(&Movement::Drop));
+    }
+
+    #[test]
+    fn follows_path() {
+        let world = world(&[(6, 64, 4, STONE)]);
+        let path = find_path(
+            &world,
+            position(4, 64, 4),
+            position(8, 64, 4),
+            &PathConfig::default(),
+        )
+        .unwrap();
+
+        let mut physics = PlayerPhysics::new();
+        physics.x = 4.5;
+        physics.y = 64.0;
+        physics.z = 4.5;
+        let mut follower = PathFollower::new(path);
+        for _ in 0..200 {
+            match follower.steer(&physics) {
+                Some((input, yaw)) => {
+                    physics.yaw = yaw;
+                    physics.tick(&world, &input);
+                }
+                None => break,
+            }
+        }
+        assert!(follower.is_finished());
+        assert!((physics.x - 8.5).abs() < 0.35);
+        assert_eq!(physics.y, 64.0);
+    }
+}
@@ -0,0 +1,464 @@
+use crate::world::block::{Aabb, BlockShape};
+use crate::world::World;
+
+const PLAYER_WIDTH: f64 = 0.6;
+const PLAYER_HEIGHT: f64 = 1.8;
+const SNEAKING_HEIGHT: f64 = 1.5;
+const STEP_HEIGHT: f64 = 0.6;
+
+const GRAVITY: f64 = 0.08;
+const AIR_DRAG: f64 = 0.98;
+const DEFAULT_SLIPPERINESS: f64 = 0.6;
+const JUMP_VELOCITY: f64 = 0.42;
+const SPRINT_JUMP_BOOST: f64 = 0.2;
+const JUMP_DELAY: u32 = 10;
+const SPRINT_MULTIPLIER: f64 = 1.3;
+const SNEAK_MULTIPLIER: f64 = 0.3;
+const CLIMB_SPEED: f64 = 0.2;
+const MAX_CLIMB_FALL_SPEED: f64 = 0.15;
+const MIN_VELOCITY: f64 = 0.003;
+
+/// Movement keys held by the player during a tick.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MovementInput {
+    /// -1.0 (backward) to 1.0 (forward).
+    pub forward: f32,
+    /// -1.0 (right) to 1.0 (left).
+    pub strafe: f32,
+    pub jump: bool,
+    pub sprint: bool,
+    pub sneak: bool,
+}
+
+/// Tick-based simulation of the player's own movement, following the vanilla client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerPhysics {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Velocity in blocks per tick.
+    pub velocity: (f64, f64, f64),
+    pub on_ground: bool,
+    /// Base value of the `minecraft:generic.movement_speed` attribute, without sprinting.
+    pub movement_speed: f64,
+    pub sprinting: bool,
+    pub sneaking: bool,
+    horizontal_collision: bool,
+    jump_delay: u32,
+}
+
+impl PlayerPhysics {
+    pub fn new() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            yaw: 0.0,
+            pitch: 0.0,
+            velocity: (0.0, 0.0, 0.0),
+            on_ground: true,
+            movement_speed: 0.1,
+            sprinting: false,
+            sneaking: false,
+            horizontal_collision: false,
+            jump_delay: 0,
+        }
+    }
+
+    pub fn position(&self) -> (f64, f64, f64) {
+        (self.x, self.y, self.z)
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        let height = if self.sneaking {
+            SNEAKING_HEIGHT
+        } else {
+            PLAYER_HEIGHT
+        };
+        let half_width = PLAYER_WIDTH / 2.0;
+        Aabb::new(
+            (self.x - half_width, self.y, self.z - half_width),
+            (self.x + half_width, self.y + height, self.z + half_width),
+        )
+    }
+
+    pub fn in_water(&self, world: &World) -> bool {
+        world.any_shape(&self.bounding_box().inflate(-0.001), |shape| {
+            shape == BlockShape::Water
+        })
+    }
+
+    pub fn in_lava(&self, world: &World) -> bool {
+        world.any_shape(&self.bounding_box().inflate(-0.001), |shape| {
+            shape == BlockShape::Lava
+        })
+    }
+
+    pub fn on_climbable(&self, world: &World) -> bool {
+        world.any_shape(&self.bounding_box().inflate(-0.001), |shape| {
+            shape.is_climbable()
+        })
+    }
+
+    /// Advances the simulation by one tick.
+    pub fn tick(&mut self, world: &World, input: &MovementInput) {
+        let (vx, vy, vz) = self.velocity;
+        let clamp = |v: f64| if v.abs() < MIN_VELOCITY { 0.0 } else { v };
+        self.velocity = (clamp(vx), clamp(vy), clamp(vz));
+
+        let mut forward = input.forward as f64;
+        let mut strafe = input.strafe as f64;
+        self.sneaking = input.sneak;
+        if self.sneaking {
+            forward *= SNEAK_MULTIPLIER;
+            strafe *= SNEAK_MULTIPLIER;
+        }
+        forward *= 0.98;
+        strafe *= 0.98;
+        self.sprinting = input.sprint && input.forward > 0.0 && !self.sneaking;
+
+        let in_water = self.in_water(world);
+        let in_lava = self.in_lava(world);
+        if self.jump_delay > 0 {
+            self.jump_delay -= 1;
+        }
+        if input.jump {
+            if in_water || in_lava {
+                self.velocity.1 += 0.04;
+            } else if self.on_ground && self.jump_delay == 0 {
+                self.jump();
+                self.jump_delay = JUMP_DELAY;
+            }
+        } else {
+            self.jump_delay = 0;
+        }
+
+        if in_water || in_lava {
+            self.move_relative(0.02, forward, strafe);
+            self.move_by(world);
+            let (drag, fall) = if in_water {
+                (0.8, if self.sprinting { 0.0 } else { GRAVITY / 16.0 })
+            } else {
+                (0.5, GRAVITY / 4.0)
+            };
+            let horizontal_drag = if in_water && self.sprinting {
+                0.9
+            } else {
+                drag
+            };
+            self.velocity.0 *= horizontal_drag;
+            self.velocity.1 = self.velocity.1 * drag - fall;
+            self.velocity.2 *= horizontal_drag;
+            if self.horizontal_collision {
+                self.velocity.1 = 0.3;
+            }
+        } else {
+            let slipperiness = if self.on_ground {
+                DEFAULT_SLIPPERINESS
+            } else {
+                1.0
+            };
+            let friction = slipperiness * 0.91;
+            let acceleration = if self.on_ground {
+                self.speed() * (0.216 / (slipperiness * slipperiness * slipperiness))
+            } else if self.sprinting {
+                0.026
+            } else {
+                0.02
+            };
+            self.move_relative(acceleration, forward, strafe);
+
+            let climbing = self.on_climbable(world);
+            if climbing {
+                let limit = MAX_CLIMB_FALL_SPEED;
+                self.velocity.0 = self.velocity.0.clamp(-limit, limit);
+                self.velocity.2 = self.velocity.2.clamp(-limit, limit);
+                self.velocity.1 = self.velocity.1.max(-limit);
+                if self.sneaking && self.velocity.1 < 0.0 {
+                    self.velocity.1 = 0.0;
+                }
+            }
+            self.move_by(world);
+            if climbing && (self.horizontal_collision || input.jump) {
+                self.velocity.1 = CLIMB_SPEED;
+            }
+
+            self.velocity.0 *= friction;
+            self.velocity.1 = (self.velocity.1 - GRAVITY) * AIR_DRAG;
+            self.velocity.2 *= friction;
+        }
+    }
+
+    fn speed(&self) -> f64 {
+        if self.sprinting {
+            self.movement_speed * SPRINT_MULTIPLIER
+        } else {
+            self.movement_speed
+        }
+    }
+
+    fn jump(&mut self) {
+        self.velocity.1 = JUMP_VELOCITY;
+        if self.sprinting {
+            let yaw = (self.yaw as f64).to_radians();
+            self.velocity.0 -= yaw.sin() * SPRINT_JUMP_BOOST;
+            self.velocity.2 += yaw.cos() * SPRINT_JUMP_BOOST;
+        }
+    }
+
+    /// Accelerates in the direction of the input, relative to the player's yaw.
+    fn move_relative(&mut self, acceleration: f64, forward: f64, strafe: f64) {
+        let length_squared = forward * forward + strafe * strafe;
+        if length_squared < 1.0e-7 {
+            return;
+        }
+        let scale = acceleration / length_squared.sqrt().max(1.0);
+        let (forward, strafe) = (forward * scale, strafe * scale);
+        let yaw = (self.yaw as f64).to_radians();
+        let (sin, cos) = (yaw.sin(), yaw.cos());
+        self.velocity.0 += strafe * cos - forward * sin;
+        self.velocity.2 += forward * cos + strafe * sin;
+    }
+
+    /// Moves by the current velocity, colliding with blocks.
+    fn move_by(&mut self, world: &World) {
+        let (mut dx, dy, mut dz) = self.velocity;
+        if self.sneaking && self.on_ground {
+            let (x, z) = self.back_off_from_edge(world, dx, dz);
+            dx = x;
+            dz = z;
+        }
+
+        let bounding_box = self.bounding_box();
+        let (mut mx, mut my, mut mz) = collide(world, &bounding_box, dx, dy, dz);
+
+        let falling = dy < 0.0 && my != dy;
+        if (self.on_ground || falling) && (mx != dx || mz != dz) {
+            let (sx, sy, sz) = collide(world, &bounding_box, dx, STEP_HEIGHT, dz);
+            let stepped = bounding_box.offset(sx, sy, sz);
+            let down = collide(world, &stepped, 0.0, -sy + dy.min(0.0), 0.0).1;
+            if sx * sx + sz * sz > mx * mx + mz * mz {
+                mx = sx;
+                my = sy + down;
+                mz = sz;
+            }
+        }
+
+        self.x += mx;
+        self.y += my;
+        self.z += mz;
+        self.horizontal_collision = mx != dx || mz != dz;
+        self.on_ground = dy < 0.0 && my != dy;
+        if mx != dx {
+            self.velocity.0 = 0.0;
+        }
+        if my != dy {
+            self.velocity.1 = 0.0;
+        }
+        if mz != dz {
+            self.velocity.2 = 0.0;
+        }
+    }
+
+    /// Shortens a movement so that a sneaking player does not walk off a ledge.
+    fn back_off_from_edge(&self, world: &World, mut dx: f64, mut dz: f64) -> (f64, f64) {
+        const STEP: f64 = 0.05;
+        let bounding_box = self.bounding_box();
+        let unsupported = |dx: f64, dz: f64| {
+            world
+                .collision_boxes(&bounding_box.offset(dx, -STEP_HEIGHT, dz))
+                .is_empty()
+        };
+        let approach = |v: f64| {
+            if v.abs() < STEP {
+                0.0
+            } else {
+                v - STEP * v.signum()
+            }
+        };
+        while dx != 0.0 && unsupported(dx, 0.0) {
+            dx = approach(dx);
+        }
+        while dz != 0.0 && unsupported(0.0, dz) {
+            dz = approach(dz);
+        }
+        while dx != 0.0 && dz != 0.0 && unsupported(dx, dz) {
+            dx = approach(dx);
+            dz = approach(dz);
+        }
+        (dx, dz)
+    }
+}
+
+impl Default for PlayerPhysics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Limits a movement of `bounding_box` so that it does not enter any block.
+fn collide(world: &World, bounding_box: &Aabb, dx: f64, dy: f64, dz: f64) -> (f64, f64, f64) {
+    let boxes = world.collision_boxes(&bounding_box.expand_towards(dx, dy, dz));
+    let mut moved = *bounding_box;
+
+    let dy = boxes.iter().fold(dy, |dy, other| moved.clip_y(other, dy));
+    moved = moved.offset(0.0, dy, 0.0);
+
+    // The larger horizontal movement is resolved first.
+    let (dx, dz) = if dx.abs() < dz.abs() {
+        let dz = boxes.iter().fold(dz, |dz, other| moved.clip_z(other, dz));
+        moved = moved.offset(0.0, 0.0, dz);
+        let dx = boxes.iter().fold(dx, |dx, other| moved.clip_x(other, dx));
+        (dx, dz)
+    } else {
+        let dx = boxes.iter().fold(dx, |dx, other| moved.clip_x(other, dx));
+        moved = moved.offset(dx, 0.0, 0.0);
+        let dz = boxes.iter().fold(dz, |dz, other| moved.clip_z(other, dz));
+        (dx, dz)
+    };
+    (dx, dy, dz)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::types::Position;
+    use crate::world::test_world as world;
+
+    const STONE: i32 = 1;
+    const WATER: i32 = 34;
+    /// A bottom smooth stone slab.
+    const SLAB: i32 = 8349;
+    /// An oak fence connected to the east and west.
+    const FENCE: i32 = 3966 + 0b01110;
+
+    fn player(x: f64, y: f64, z: f64) -> PlayerPhysics {
+        PlayerPhysics {
+            x,
+            y,
+            z,
+            ..PlayerPhysics::new()
+        }
+    }
+
+    #[test]
+    fn falls_and_lands() {
+        let world = world(&[]);
+        let mut physics = player(8.5, 70.0, 8.5);
+        physics.on_ground = false;
+        for _ in 0..40 {
+            physics.tick(&world, &MovementInput::default());
+        }
+        assert_eq!(physics.y, 64.0);
+        assert!(physics.on_ground);
+        // Gravity is applied every tick, even while standing.
+        assert!((physics.velocity.1 + GRAVITY * AIR_DRAG).abs() < 1e-9);
+    }
+
+    #[test]
+    fn walks_and_steps_up() {
+        // Walking along +Z (yaw 0) into a one block step at z = 10.
+        let blocks: Vec<_> = (0..16)
+            .flat_map(|x| (10..16).map(move |z| (x, 64, z, STONE)))
+            .collect();
+        let world = world(&blocks);
+        let mut physics = player(8.5, 64.0, 8.5);
+        let input = MovementInput {
+            forward: 1.0,
+            ..MovementInput::default()
+        };
+
+        // A block is too high to step onto, so the player stops against it.
+        for _ in 0..20 {
+            physics.tick(&world, &input);
+        }
+        assert!((physics.z - 9.7).abs() < 1e-9);
+        assert_eq!(physics.y, 64.0);
+
+        // Jumping gets the player on top.
+        let jump = MovementInput {
+            jump: true,
+            ..input
+        };
+        physics.tick(&world, &jump);
+        for _ in 0..20 {
+            physics.tick(&world, &input);
+        }
+        assert_eq!(physics.y, 65.0);
+        assert!(physics.z > 10.0);
+    }
+
+    #[test]
+    fn steps_onto_slab() {
+        let slabs: Vec<_> = (0..16).map(|x| (x, 64, 10, SLAB)).collect();
+        let world = world(&slabs);
+        let mut physics = player(8.5, 64.0, 8.5);
+        let input = MovementInput {
+            forward: 1.0,
+            ..MovementInput::default()
+        };
+        for _ in 0..12 {
+            physics.tick(&world, &input);
+        }
+        assert_eq!(physics.y, 64.5);
+        assert!(physics.z > 10.0 && physics.z < 11.0);
+    }
+
+    #[test]
+    fn cannot_jump_over_fence() {
+        let fence: Vec<_> = (0..16).map(|x| (x, 64, 10, FENCE)).collect();
+        let world = world(&fence);
+        let mut physics = player(8.5, 64.0, 8.5);
+        let input = MovementInput {
+            forward: 1.0,
+            jump: true,
+            ..MovementInput::default()
+        };
+        for _ in 0..40 {
+            physics.tick(&world, &input);
+        }
+        assert!(physics.z < 10.1);
+        assert!(physics.y < 65.5);
+    }
+
+    #[test]
+    fn sneaking_stops_at_edge() {
+        let mut world = world(&[]);
+        for x in 0..16 {
+            world.set_block(Position { x, y: 63, z: 10 }, 0);
+        }
+        let mut physics = player(8.5, 64.0, 8.5);
+        let input = MovementInput {
+            forward: 1.0,
+            sneak: true,
+            ..MovementInput::default()
+        };
+        for _ in 0..100 {
+            physics.tick(&world, &input);
+        }
+        assert_eq!(physics.y, 64.0);
+        assert!(physics.z > 9.5 && physics.z < 10.3);
+    }
+
+    #[test]
+    fn sinks_slowly_in_water() {
+        let blocks: Vec<_> = (64..70).map(|y| (8, y, 8, WATER)).collect();
+        let world = world(&blocks);
+        let mut physics = player(8.5, 68.0, 8.5);
+        physics.on_ground = false;
+        physics.tick(&world, &MovementInput::default());
+        assert!(physics.in_water(&world));
+        assert!(physics.velocity.1 > -0.01 && physics.velocity.1 < 0.0);
+
+        let swim = MovementInput {
+            jump: true,
+            ..MovementInput::default()
+        };
+        for _ in 0..5 {
+            physics.tick(&world, &swim);
+        }
+        assert!(physics.y > 68.0);
+    }
+}
@@ -0,0 +1,255 @@
+use super::{AesCfb8, PacketFrame, TransportSession, MAX_FRAME_LEN};
+use cfb8::stream_cipher::{NewStreamCipher, StreamCipher};
+use declio::{Decode, Encode};
+use std::convert::TryInto;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+
+/// The buffer that the synchronous session used for encoding writes to.
+pub(crate) type Encoder = TransportSession<io::Empty, Vec<u8>>;
+
+/// A `TransportSession` over `AsyncRead` and `AsyncWrite` streams.
+///
+/// Packets are encoded, compressed and encrypted into a buffer by a synchronous session and then
+/// written out by `flush`, so the same packet definitions and cipher state are shared by both.
+pub struct AsyncTransportSession<R = OwnedReadHalf, W = OwnedWriteHalf> {
+    reader: FrameReader<R>,
+    writer: W,
+    encoder: Encoder,
+}
+
+impl AsyncTransportSession {
+    pub async fn connect(host: &str, port: u16) -> anyhow::Result<Self> {
+        let (reader, writer) = TcpStream::connect((host, port)).await?.into_split();
+        Ok(Self::new(reader, writer))
+    }
+}
+
+impl<R, W> AsyncTransportSession<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self {
+            reader: FrameReader::new(reader),
+            writer,
+            encoder: TransportSession::new(io::empty(), Vec::new()),
+        }
+    }
+
+    /// Encodes a packet to be sent by the next `flush`.
+    pub fn queue_packet<T>(&mut self, packet: &T) -> anyhow::Result<()>
+    where
+        T: Encode,
+    {
+        self.encoder.write_packet(packet)
+    }
+
+    /// Sends all queued packets.
+    pub async fn flush(&mut self) -> anyhow::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        flush(&mut self.encoder, &mut self.writer).await
+    }
+
+    pub async fn write_packet<T>(&mut self, packet: &T) -> anyhow::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+        T: Encode,
+    {
+        self.queue_packet(packet)?;
+        self.flush().await
+    }
+
+    /// Reads the next packet.
+    ///
+    /// This is cancel safe: if the future is dropped before completing, no data is lost and the
+    /// next call continues where it left off.
+    pub async fn read_packet<T>(&mut self) -> anyhow::Result<T>
+    where
+        R: AsyncRead + Unpin,
+        T: Decode,
+    {
+        self.reader.read_packet().await
+    }
+
+    /// Reads the next packet without decoding it. This is cancel safe, like `read_packet`.
+    pub async fn read_frame(&mut self) -> anyhow::Result<PacketFrame>
+    where
+        R: AsyncRead + Unpin,
+    {
+        self.reader.read_frame().await
+    }
+
+    /// Encodes a frame to be sent by the next `flush`.
+    pub fn queue_frame(&mut self, frame: &PacketFrame) -> anyhow::Result<()> {
+        self.encoder.write_frame(frame)
+    }
+
+    pub fn enable_encryption(&mut self, shared_secret: [u8; 16]) -> anyhow::Result<()> {
+        self.reader
+            .set_cipher(AesCfb8::new_var(&shared_secret, &shared_secret).unwrap())?;
+        self.encoder.enable_encryption(shared_secret)
+    }
+
+    pub fn set_compression_threshold(&mut self, threshold: Option<usize>) {
+        self.reader.compressed = threshold.is_some();
+        self.encoder.set_compression_threshold(threshold);
+    }
+
+    pub(crate) fn into_parts(self) -> (FrameReader<R>, W, Encoder) {
+        (self.reader, self.writer, self.encoder)
+    }
+}
+
+/// Writes everything the encoder has buffered.
+///
+/// Bytes are only removed from the buffer once written, so this is cancel safe.
+pub(crate) async fn flush<W>(encoder: &mut Encoder, writer: &mut W) -> anyhow::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let buf = encoder.written_mut();
+    if buf.is_empty() {
+        return Ok(());
+    }
+    while !buf.is_empty() {
+        let len = writer.write(buf).await?;
+        if len == 0 {
+            return Err(io::Error::from(io::ErrorKind::WriteZero).into());
+        }
+        buf.drain(..len);
+    }
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Splits the incoming stream into decrypted frames.
+pub(crate) struct FrameReader<R> {
+    inner: R,
+    encryption: Option<AesCfb8>,
+    compressed: bool,
+    /// Decrypted bytes that are not part of a complete frame yet.
+    buffer: Vec<u8>,
+}
+
+impl<R> FrameReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            encryption: None,
+            compressed: false,
+            buffer: Vec::new(),
+        }
+    }
+
+    fn set_cipher(&mut self, mut cipher: AesCfb8) -> anyhow::Result<()> {
+        if self.encryption.is_some() {
+            return Err(anyhow::Error::msg("encryption is already enabled"));
+        }
+        // Anything read ahead of the packet that enabled encryption is already encrypted.
+        cipher.decrypt(&mut self.buffer);
+        self.encryption = Some(cipher);
+        Ok(())
+    }
+
+    pub(crate) async fn read_packet<T>(&mut self) -> anyhow::Result<T>
+    where
+        R: AsyncRead + Unpin,
+        T: Decode,
+    {
+        self.read_frame().await?.decode()
+    }
+
+    pub(crate) async fn read_frame(&mut self) -> anyhow::Result<PacketFrame>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut chunk = [0; 4096];
+        loop {
+            if let Some((header_len, len)) = frame_header(&self.buffer)? {
+                if self.buffer.len() >= header_len + len {
+                    let frame = PacketFrame::from_body(
+                        &self.buffer[header_len..header_len + len],
+                        self.compressed,
+                    );
+                    self.buffer.drain(..header_len + len);
+                    return frame;
+                }
+            }
+            let len = self.inner.read(&mut chunk).await?;
+            if len == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            if let Some(cipher) = &mut self.encryption {
+                cipher.decrypt(&mut chunk[..len]);
+            }
+            self.buffer.extend_from_slice(&chunk[..len]);
+        }
+    }
+}
+
+/// Parses the length prefix of a frame, returning its size and the length of the frame, or
+/// `None` if more bytes are needed.
+fn frame_header(buffer: &[u8]) -> anyhow::Result<Option<(usize, usize)>> {
+    let mut value: u32 = 0;
+    for i in 0..5 {
+        let byte = match buffer.get(i) {
+            Some(&byte) => byte,
+            None => return Ok(None),
+        };
+        value |= ((byte & 0x7f) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            let len: usize = (value as i32).try_into()?;
+            if len > MAX_FRAME_LEN {
+                return Err(anyhow::Error::msg(format!(
+                    "frame is too long ({} bytes)",
+                    len
+                )));
+            }
+            return Ok(Some((i + 1, len)));
+        }
+    }
+    Err(anyhow::Error::msg("packet length is too long"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::status::{Clientbound, Serverbound};
+
+    #[tokio::test]
+    async fn interoperates_with_blocking_session() {
+        let secret = [7; 16];
+        let packets = [
+            Clientbound::Pong { payload: 1 },
+            Clientbound::Pong { payload: 2 },
+        ];
+
+        let mut server = TransportSession::new(io::empty(), Vec::new());
+        server.write_packet(&packets[0]).unwrap();
+        server.set_compression_threshold(Some(0));
+        server.enable_encryption(secret).unwrap();
+        server.write_packet(&packets[1]).unwrap();
+        let incoming = std::mem::take(server.written_mut());
+
+        let mut session = AsyncTransportSession::new(incoming.as_slice(), Vec::new());
+        let first: Clientbound = session.read_packet().await.unwrap();
+        assert_eq!(first, packets[0]);
+        session.set_compression_threshold(Some(0));
+        session.enable_encryption(secret).unwrap();
+        let second: Clientbound = session.read_packet().await.unwrap();
+        assert_eq!(second, packets[1]);
+        assert!(session.read_packet::<Clientbound>().await.is_err());
+
+        session
+            .write_packet(&Serverbound::Ping { payload: 3 })
+            .await
+            .unwrap();
+        let mut server = TransportSession::new(session.writer.as_slice(), io::sink());
+        server.set_compression_threshold(Some(0));
+        server.enable_encryption(secret).unwrap();
+        let ping: Serverbound = server.read_packet().unwrap();
+        assert_eq!(ping, Serverbound::Ping { payload: 3 });
+    }
+}
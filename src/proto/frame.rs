@@ -0,0 +1,239 @@
+use super::types::VarInt;
+use declio::{Decode, Encode};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// The largest frame the vanilla server accepts, whose length fits in a 3-byte VarInt.
+pub const MAX_FRAME_LEN: usize = (1 << 21) - 1;
+
+/// The largest decompressed packet the vanilla client accepts.
+pub const MAX_PACKET_LEN: usize = 1 << 23;
+
+/// A packet that has been read off the wire but not decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacketFrame {
+    pub id: i32,
+    /// The packet's fields, after the ID.
+    pub payload: Vec<u8>,
+}
+
+impl PacketFrame {
+    pub fn new(id: i32, payload: Vec<u8>) -> Self {
+        Self { id, payload }
+    }
+
+    /// Encodes a packet, splitting off its ID.
+    pub fn from_packet<T>(packet: &T) -> anyhow::Result<Self>
+    where
+        T: Encode,
+    {
+        let mut buf = Vec::new();
+        packet.encode((), &mut buf)?;
+        Self::from_data(&buf)
+    }
+
+    /// Splits the ID off an uncompressed packet.
+    pub fn from_data(mut data: &[u8]) -> anyhow::Result<Self> {
+        let id = VarInt::decode((), &mut data)?.0;
+        Ok(Self::new(id, data.to_vec()))
+    }
+
+    /// Decodes the packet, which must use up exactly the whole payload.
+    pub fn decode<T>(&self) -> anyhow::Result<T>
+    where
+        T: Decode,
+    {
+        let mut id = Vec::new();
+        VarInt(self.id).encode((), &mut id)?;
+        let mut reader = id.as_slice().chain(self.payload.as_slice());
+        let result = T::decode((), &mut reader);
+
+        let remaining = reader.into_inner().1.len();
+        let offset = self.payload.len() - remaining;
+        match result {
+            Ok(_) if remaining != 0 => Err(FrameError::TrailingBytes {
+                id: self.id,
+                offset,
+                len: remaining,
+            }
+            .into()),
+            Ok(packet) => Ok(packet),
+            Err(source) => Err(FrameError::Decode {
+                id: self.id,
+                offset,
+                source,
+            }
+            .into()),
+        }
+    }
+
+    /// Reads a whole frame, decompressing it if compression is enabled.
+    pub fn read<R>(mut reader: R, compressed: bool) -> anyhow::Result<Self>
+    where
+        R: io::Read,
+    {
+        let len: usize = VarInt::decode((), &mut reader)?.0.try_into()?;
+        if len > MAX_FRAME_LEN {
+            return Err(anyhow::Error::msg(format!(
+                "frame is too long ({} bytes)",
+                len
+            )));
+        }
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body)?;
+        Self::from_body(&body, compressed)
+    }
+
+    /// Parses the contents of a frame, after its length prefix.
+    pub(crate) fn from_body(mut body: &[u8], compressed: bool) -> anyhow::Result<Self> {
+        if !compressed {
+            return Self::from_data(body);
+        }
+        let uncompressed_len: usize = VarInt::decode((), &mut body)?.0.try_into()?;
+        if uncompressed_len == 0 {
+            return Self::from_data(body);
+        }
+        if uncompressed_len > MAX_PACKET_LEN {
+            return Err(anyhow::Error::msg(format!(
+                "packet is too long ({} bytes)",
+                uncompressed_len
+            )));
+        }
+        let mut data = Vec::with_capacity(uncompressed_len);
+        ZlibDecoder::new(body)
+            .take(uncompressed_len as u64 + 1)
+            .read_to_end(&mut data)?;
+        if data.len() != uncompressed_len {
+            return Err(anyhow::Error::msg(format!(
+                "packet decompressed to {} bytes, expected {}",
+                data.len(),
+                uncompressed_len
+            )));
+        }
+        Self::from_data(&data)
+    }
+
+    /// Writes the frame, compressing it if it is at least `compression_threshold` bytes long.
+    pub fn write<W>(
+        &self,
+        mut writer: W,
+        compression_threshold: Option<usize>,
+    ) -> anyhow::Result<()>
+    where
+        W: io::Write,
+    {
+        let mut data_buf = Vec::with_capacity(self.payload.len() + 5);
+        VarInt(self.id).encode((), &mut data_buf)?;
+        data_buf.extend_from_slice(&self.payload);
+
+        let mut header_buf = Vec::new();
+        if let Some(threshold) = compression_threshold {
+            if data_buf.len() < threshold {
+                VarInt(0).encode((), &mut header_buf)?;
+            } else {
+                VarInt(data_buf.len().try_into()?).encode((), &mut header_buf)?;
+
+                // Compress the data buffer, and replace it with the compressed version.
+                let mut compressed_writer = ZlibEncoder::new(Vec::new(), Compression::default());
+                compressed_writer.write_all(&data_buf)?;
+                data_buf = compressed_writer.finish()?;
+            }
+        }
+
+        VarInt((header_buf.len() + data_buf.len()).try_into()?).encode((), &mut writer)?;
+        writer.write_all(&header_buf)?;
+        writer.write_all(&data_buf)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// A packet whose fields do not match its frame.
+#[derive(Debug)]
+pub enum FrameError {
+    /// The parser failed, possibly by reading past the end of the frame.
+    Decode {
+        id: i32,
+        /// How far into the payload the parser got.
+        offset: usize,
+        source: declio::Error,
+    },
+    /// The parser finished before the end of the frame.
+    TrailingBytes { id: i32, offset: usize, len: usize },
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Decode { id, offset, source } => write!(
+                f,
+                "failed to decode packet {:#04x} at offset {}: {}",
+                id, offset, source
+            ),
+            Self::TrailingBytes { id, offset, len } => write!(
+                f,
+                "packet {:#04x} has {} bytes left over at offset {}",
+                id, len, offset
+            ),
+        }
+    }
+}
+
+impl Error for FrameError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Decode { source, .. } => Some(source),
+            Self::TrailingBytes { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::status::Clientbound;
+
+    #[test]
+    fn round_trip() {
+        let packet = Clientbound::Pong { payload: 42 };
+        let frame = PacketFrame::from_packet(&packet).unwrap();
+        assert_eq!(frame.id, 0x01);
+        assert_eq!(frame.payload.len(), 8);
+
+        for &threshold in &[None, Some(0), Some(256)] {
+            let mut buf = Vec::new();
+            frame.write(&mut buf, threshold).unwrap();
+            let read = PacketFrame::read(buf.as_slice(), threshold.is_some()).unwrap();
+            assert_eq!(read, frame);
+            assert_eq!(read.decode::<Clientbound>().unwrap(), packet);
+        }
+    }
+
+    #[test]
+    fn mismatched_length() {
+        let error = PacketFrame::new(0x01, vec![0; 9])
+            .decode::<Clientbound>()
+            .unwrap_err();
+        match error.downcast_ref::<FrameError>() {
+            Some(&FrameError::TrailingBytes { id, offset, len }) => {
+                assert_eq!((id, offset, len), (0x01, 8, 1));
+            }
+            _ => panic!("unexpected error: {}", error),
+        }
+
+        let error = PacketFrame::new(0x01, vec![0; 5])
+            .decode::<Clientbound>()
+            .unwrap_err();
+        match error.downcast_ref::<FrameError>() {
+            Some(&FrameError::Decode { id, offset, .. }) => {
+                assert_eq!((id, offset), (0x01, 5));
+            }
+            _ => panic!("unexpected error: {}", error),
+        }
+    }
+}
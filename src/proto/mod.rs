@@ -4,16 +4,20 @@ pub mod play;
 pub mod status;
 pub mod types;
 
-use self::types::VarInt;
+#[cfg(feature = "async")]
+mod async_session;
+mod frame;
+
+#[cfg(feature = "async")]
+pub use self::async_session::AsyncTransportSession;
+#[cfg(feature = "async")]
+pub(crate) use self::async_session::{flush, FrameReader};
+pub use self::frame::{FrameError, PacketFrame, MAX_FRAME_LEN, MAX_PACKET_LEN};
 use aes::Aes128;
 use cfb8::stream_cipher::{NewStreamCipher, StreamCipher};
 use cfb8::Cfb8;
 use declio::{Decode, Encode};
-use flate2::read::ZlibDecoder;
-use flate2::write::ZlibEncoder;
-use flate2::Compression;
-use std::convert::TryInto;
-use std::io::{self, Read, Write};
+use std::io;
 use std::net::TcpStream;
 
 type AesCfb8 = Cfb8<Aes128>;
@@ -167,11 +171,7 @@ impl<R, W> TransportSession<R, W> {
         W: io::Write,
         T: Encode,
     {
-        if let Some(threshold) = self.compression_threshold {
-            write_compressed_packet(packet, &mut self.writer, threshold)
-        } else {
-            write_packet(packet, &mut self.writer)
-        }
+        self.write_frame(&PacketFrame::from_packet(packet)?)
     }
 
     pub fn read_packet<T>(&mut self) -> anyhow::Result<T>
@@ -179,20 +179,40 @@ impl<R, W> TransportSession<R, W> {
         R: io::Read,
         T: Decode,
     {
-        if let Some(_threshold) = self.compression_threshold {
-            read_compressed_packet(&mut self.reader)
-        } else {
-            read_packet(&mut self.reader)
-        }
+        self.read_frame()?.decode()
     }
 
     pub fn try_read_packet<T>(&mut self) -> anyhow::Result<Option<T>>
     where
         R: io::Read + Peekable,
         T: Decode,
+    {
+        self.try_read_frame()?
+            .map(|frame| frame.decode())
+            .transpose()
+    }
+
+    pub fn write_frame(&mut self, frame: &PacketFrame) -> anyhow::Result<()>
+    where
+        W: io::Write,
+    {
+        frame.write(&mut self.writer, self.compression_threshold)
+    }
+
+    /// Reads the next packet without decoding it.
+    pub fn read_frame(&mut self) -> anyhow::Result<PacketFrame>
+    where
+        R: io::Read,
+    {
+        PacketFrame::read(&mut self.reader, self.compression_threshold.is_some())
+    }
+
+    pub fn try_read_frame(&mut self) -> anyhow::Result<Option<PacketFrame>>
+    where
+        R: io::Read + Peekable,
     {
         if self.reader.can_read()? {
-            self.read_packet().map(Some)
+            self.read_frame().map(Some)
         } else {
             Ok(None)
         }
@@ -211,80 +231,42 @@ impl<R, W> TransportSession<R, W> {
     }
 }
 
-pub fn read_packet<T, R>(mut reader: R) -> anyhow::Result<T>
+#[cfg(feature = "async")]
+impl<R> TransportSession<R, Vec<u8>> {
+    /// The bytes written and not yet taken out.
+    pub(crate) fn written_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.writer.inner
+    }
+}
+
+pub fn read_packet<T, R>(reader: R) -> anyhow::Result<T>
 where
     T: Decode,
     R: io::Read,
 {
-    let len = VarInt::decode((), &mut reader)?.0;
-    let mut reader = reader.take(len.try_into()?);
-    let packet = T::decode((), &mut reader)?;
-
-    let remaining_bytes = reader.bytes().filter_map(Result::ok).count();
-    debug_assert!(remaining_bytes == 0, "not all bytes read by packet parser");
-    Ok(packet)
+    PacketFrame::read(reader, false)?.decode()
 }
 
-pub fn write_packet<T, W>(packet: &T, mut writer: W) -> anyhow::Result<()>
+pub fn write_packet<T, W>(packet: &T, writer: W) -> anyhow::Result<()>
 where
     T: Encode,
     W: io::Write,
 {
-    let mut buf = Vec::new();
-    packet.encode((), &mut buf)?;
-    VarInt(buf.len().try_into()?).encode((), &mut writer)?;
-    writer.write_all(&buf)?;
-    writer.flush()?;
-    Ok(())
+    PacketFrame::from_packet(packet)?.write(writer, None)
 }
 
-pub fn read_compressed_packet<T, R>(mut reader: R) -> anyhow::Result<T>
+pub fn read_compressed_packet<T, R>(reader: R) -> anyhow::Result<T>
 where
     T: Decode,
     R: io::Read,
 {
-    let len = VarInt::decode((), &mut reader)?.0;
-    let mut reader = reader.take(len.try_into()?);
-    let uncompressed_len = VarInt::decode((), &mut reader)?.0;
-    let packet;
-    if uncompressed_len == 0 {
-        packet = T::decode((), &mut reader)?;
-    } else {
-        let mut reader = ZlibDecoder::new(&mut reader);
-        packet = T::decode((), &mut reader)?;
-    }
-    let remaining_bytes = reader.bytes().filter_map(Result::ok).count();
-    debug_assert!(remaining_bytes == 0, "not all bytes read by packet parser");
-    Ok(packet)
+    PacketFrame::read(reader, true)?.decode()
 }
 
-pub fn write_compressed_packet<T, W>(
-    packet: &T,
-    mut writer: W,
-    threshold: usize,
-) -> anyhow::Result<()>
+pub fn write_compressed_packet<T, W>(packet: &T, writer: W, threshold: usize) -> anyhow::Result<()>
 where
     T: Encode,
     W: io::Write,
 {
-    let mut data_buf = Vec::new();
-    packet.encode((), &mut data_buf)?;
-
-    let mut header_buf = Vec::new();
-    if data_buf.len() < threshold {
-        VarInt(0).encode((), &mut header_buf)?;
-    } else {
-        VarInt(data_buf.len().try_into()?).encode((), &mut header_buf)?;
-
-        // Compress the data buffer, and replace it with the compressed version.
-        let mut compressed_writer = ZlibEncoder::new(Vec::new(), Compression::default());
-        compressed_writer.write_all(&data_buf)?;
-        data_buf = compressed_writer.finish()?;
-    }
-
-    VarInt((header_buf.len() + data_buf.len()).try_into()?).encode((), &mut writer)?;
-    writer.write_all(&header_buf)?;
-    writer.write_all(&data_buf)?;
-    writer.flush()?;
-    Ok(())
+    PacketFrame::from_packet(packet)?.write(writer, Some(threshold))
 }
@@ -1,7 +1,10 @@
 use crate::nbt::Nbt;
 use crate::proto::types::*;
 use crate::util::{Greedy, LengthPrefix};
+use declio::ctx::Len;
 use declio::{Decode, Encode};
+use std::convert::TryFrom;
+use std::io;
 
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
 #[declio(id_type = "VarInt")]
@@ -136,8 +139,9 @@ pub enum Clientbound {
 
     #[declio(id = "VarInt(0x10)")]
     DeclareCommands {
-        #[declio(with = "Greedy")]
-        todo: ByteArray,
+        #[declio(with = "LengthPrefix::<VarInt>")]
+        nodes: Vec<CommandNode>,
+        root_index: VarInt,
     },
 
     #[declio(id = "VarInt(0x11)")]
@@ -265,8 +269,8 @@ pub enum Clientbound {
         offset_z: Float,
         particle_data: Float,
         particle_count: Int,
-        #[declio(with = "Greedy")]
-        todo_data: ByteArray,
+        #[declio(ctx(decode = "*particle_id"))]
+        data: ParticleData,
     },
 
     #[declio(id = "VarInt(0x23)")]
@@ -278,8 +282,10 @@ pub enum Clientbound {
         block_light_mask: VarInt,
         empty_sky_light_mask: VarInt,
         empty_block_light_mask: VarInt,
-        #[declio(with = "Greedy")]
-        todo_light_arrays: ByteArray,
+        #[declio(ctx(decode = "Len(sky_light_mask.0.count_ones() as usize)"))]
+        sky_light_arrays: Vec<LightArray>,
+        #[declio(ctx(decode = "Len(block_light_mask.0.count_ones() as usize)"))]
+        block_light_arrays: Vec<LightArray>,
     },
 
     #[declio(id = "VarInt(0x24)")]
@@ -511,8 +517,8 @@ pub enum Clientbound {
     #[declio(id = "VarInt(0x44)")]
     EntityMetadata {
         entity_id: VarInt,
-        #[declio(with = "Greedy")]
-        todo_metadata: ByteArray,
+        #[declio(with = "metadata_list")]
+        metadata: Vec<MetadataEntry>,
     },
 
     #[declio(id = "VarInt(0x45)")]
@@ -532,8 +538,8 @@ pub enum Clientbound {
     #[declio(id = "VarInt(0x47)")]
     EntityEquipment {
         entity_id: VarInt,
-        #[declio(with = "Greedy")]
-        todo: ByteArray,
+        #[declio(with = "equipment_list")]
+        equipment: Vec<(EquipmentSlot, Slot)>,
     },
 
     #[declio(id = "VarInt(0x48)")]
@@ -643,14 +649,20 @@ pub enum Clientbound {
 
     #[declio(id = "VarInt(0x57)")]
     Advancements {
-        #[declio(with = "Greedy")]
-        todo: ByteArray,
+        reset: Boolean,
+        #[declio(with = "LengthPrefix::<VarInt>")]
+        advancements: Vec<AdvancementEntry>,
+        #[declio(with = "LengthPrefix::<VarInt>")]
+        removed: Vec<Identifier>,
+        #[declio(with = "LengthPrefix::<VarInt>")]
+        progress: Vec<AdvancementProgress>,
     },
 
     #[declio(id = "VarInt(0x58)")]
     EntityProperties {
-        #[declio(with = "Greedy")]
-        todo: ByteArray,
+        entity_id: VarInt,
+        #[declio(with = "LengthPrefix::<Int>")]
+        properties: Vec<EntityProperty>,
     },
 
     #[declio(id = "VarInt(0x59)")]
@@ -664,8 +676,8 @@ pub enum Clientbound {
 
     #[declio(id = "VarInt(0x5a)")]
     DeclareRecipes {
-        #[declio(with = "Greedy")]
-        todo: ByteArray,
+        #[declio(with = "LengthPrefix::<VarInt>")]
+        recipes: Vec<Recipe>,
     },
 
     #[declio(id = "VarInt(0x5b)")]
@@ -1029,6 +1041,9 @@ pub struct ExplosionRecord {
     pub z: Byte,
 }
 
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct LightArray(#[declio(with = "LengthPrefix::<VarInt>")] pub ByteArray);
+
 #[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
 #[declio(id_type = "UByte")]
 pub enum Gamemode {
@@ -1081,7 +1096,7 @@ pub enum CombatEvent {
     #[declio(id = "VarInt(1)")]
     EndCombat { duration: VarInt, entity_id: Int },
 
-    #[declio(id = "VarInt(1)")]
+    #[declio(id = "VarInt(2)")]
     EntityDead {
         player_id: VarInt,
         entity_id: Int,
@@ -1281,6 +1296,734 @@ pub struct Tag {
     pub entries: Vec<VarInt>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataEntry {
+    pub index: UByte,
+    pub value: MetadataValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+#[declio(id_type = "VarInt")]
+pub enum MetadataValue {
+    #[declio(id = "VarInt(0)")]
+    Byte(Byte),
+
+    #[declio(id = "VarInt(1)")]
+    VarInt(VarInt),
+
+    #[declio(id = "VarInt(2)")]
+    Float(Float),
+
+    #[declio(id = "VarInt(3)")]
+    String(String),
+
+    #[declio(id = "VarInt(4)")]
+    Chat(Chat),
+
+    #[declio(id = "VarInt(5)")]
+    OptChat {
+        present: Boolean,
+        #[declio(skip_if = "!present")]
+        value: Option<Chat>,
+    },
+
+    #[declio(id = "VarInt(6)")]
+    Slot(Slot),
+
+    #[declio(id = "VarInt(7)")]
+    Boolean(Boolean),
+
+    #[declio(id = "VarInt(8)")]
+    Rotation { x: Float, y: Float, z: Float },
+
+    #[declio(id = "VarInt(9)")]
+    Position(Position),
+
+    #[declio(id = "VarInt(10)")]
+    OptPosition {
+        present: Boolean,
+        #[declio(skip_if = "!present")]
+        value: Option<Position>,
+    },
+
+    #[declio(id = "VarInt(11)")]
+    Direction(VarInt),
+
+    #[declio(id = "VarInt(12)")]
+    OptUuid {
+        present: Boolean,
+        #[declio(skip_if = "!present")]
+        value: Option<Uuid>,
+    },
+
+    /// Block state ID, or 0 if absent.
+    #[declio(id = "VarInt(13)")]
+    OptBlockId(VarInt),
+
+    #[declio(id = "VarInt(14)")]
+    Nbt(Nbt),
+
+    #[declio(id = "VarInt(15)")]
+    Particle {
+        particle_id: VarInt,
+        #[declio(ctx(decode = "particle_id.0"))]
+        data: ParticleData,
+    },
+
+    #[declio(id = "VarInt(16)")]
+    VillagerData {
+        villager_type: VarInt,
+        villager_profession: VarInt,
+        level: VarInt,
+    },
+
+    /// Value plus one, or 0 if absent.
+    #[declio(id = "VarInt(17)")]
+    OptVarInt(VarInt),
+
+    #[declio(id = "VarInt(18)")]
+    Pose(VarInt),
+}
+
+mod metadata_list {
+    use super::*;
+
+    const END_OF_METADATA: UByte = 0xff;
+
+    pub fn encode<W>(this: &[MetadataEntry], _: (), writer: &mut W) -> Result<(), declio::Error>
+    where
+        W: io::Write,
+    {
+        for entry in this {
+            if entry.index == END_OF_METADATA {
+                return Err(declio::Error::new("invalid metadata index"));
+            }
+            entry.index.encode((), writer)?;
+            entry.value.encode((), writer)?;
+        }
+        END_OF_METADATA.encode((), writer)?;
+        Ok(())
+    }
+
+    pub fn decode<R>(_: (), reader: &mut R) -> Result<Vec<MetadataEntry>, declio::Error>
+    where
+        R: io::Read,
+    {
+        let mut acc = Vec::new();
+        loop {
+            let index = UByte::decode((), reader)?;
+            if index == END_OF_METADATA {
+                break;
+            }
+            let value = MetadataValue::decode((), reader)?;
+            acc.push(MetadataEntry { index, value });
+        }
+        Ok(acc)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct EntityProperty {
+    pub key: Identifier,
+    pub value: Double,
+    #[declio(with = "LengthPrefix::<VarInt>")]
+    pub modifiers: Vec<PropertyModifier>,
+}
+
+impl EntityProperty {
+    /// The value of the property after applying all modifiers.
+    pub fn final_value(&self) -> Double {
+        let modifiers = |operation| {
+            self.modifiers
+                .iter()
+                .filter(move |modifier| modifier.operation == operation)
+        };
+
+        let base = self.value
+            + modifiers(PropertyModifier::ADD)
+                .map(|modifier| modifier.amount)
+                .sum::<Double>();
+        let mut value = base;
+        for modifier in modifiers(PropertyModifier::MULTIPLY_BASE) {
+            value += base * modifier.amount;
+        }
+        for modifier in modifiers(PropertyModifier::MULTIPLY_TOTAL) {
+            value *= 1.0 + modifier.amount;
+        }
+        value
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct PropertyModifier {
+    pub uuid: Uuid,
+    pub amount: Double,
+    pub operation: Byte,
+}
+
+impl PropertyModifier {
+    pub const ADD: Byte = 0;
+    pub const MULTIPLY_BASE: Byte = 1;
+    pub const MULTIPLY_TOTAL: Byte = 2;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EquipmentSlot {
+    MainHand = 0,
+    OffHand = 1,
+    Boots = 2,
+    Leggings = 3,
+    Chestplate = 4,
+    Helmet = 5,
+}
+
+impl EquipmentSlot {
+    pub fn from_id(id: Byte) -> Option<Self> {
+        match id {
+            0 => Some(Self::MainHand),
+            1 => Some(Self::OffHand),
+            2 => Some(Self::Boots),
+            3 => Some(Self::Leggings),
+            4 => Some(Self::Chestplate),
+            5 => Some(Self::Helmet),
+            _ => None,
+        }
+    }
+
+    pub fn id(self) -> Byte {
+        self as Byte
+    }
+}
+
+mod equipment_list {
+    use super::*;
+
+    // Set on the slot byte of every entry except the last.
+    const HAS_NEXT: Byte = -0x80;
+
+    pub fn encode<W>(
+        this: &[(EquipmentSlot, Slot)],
+        _: (),
+        writer: &mut W,
+    ) -> Result<(), declio::Error>
+    where
+        W: io::Write,
+    {
+        if this.is_empty() {
+            return Err(declio::Error::new("equipment list must not be empty"));
+        }
+        for (i, (slot, item)) in this.iter().enumerate() {
+            let mut byte = slot.id();
+            if i + 1 < this.len() {
+                byte |= HAS_NEXT;
+            }
+            byte.encode((), writer)?;
+            item.encode((), writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn decode<R>(_: (), reader: &mut R) -> Result<Vec<(EquipmentSlot, Slot)>, declio::Error>
+    where
+        R: io::Read,
+    {
+        let mut acc = Vec::new();
+        loop {
+            let byte = Byte::decode((), reader)?;
+            let slot = EquipmentSlot::from_id(byte & !HAS_NEXT)
+                .ok_or_else(|| declio::Error::new("invalid equipment slot"))?;
+            let item = Slot::decode((), reader)?;
+            acc.push((slot, item));
+            if byte & HAS_NEXT == 0 {
+                break;
+            }
+        }
+        Ok(acc)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct CommandNode {
+    pub flags: Byte,
+    #[declio(with = "LengthPrefix::<VarInt>")]
+    pub children: Vec<VarInt>,
+    #[declio(skip_if = "*flags & Self::HAS_REDIRECT == 0")]
+    pub redirect_node: Option<VarInt>,
+    #[declio(skip_if = "*flags & Self::TYPE_MASK == Self::ROOT")]
+    pub name: Option<String>,
+    #[declio(skip_if = "*flags & Self::TYPE_MASK != Self::ARGUMENT")]
+    pub parser: Option<CommandParser>,
+    #[declio(skip_if = "*flags & Self::HAS_SUGGESTIONS_TYPE == 0")]
+    pub suggestions_type: Option<Identifier>,
+}
+
+impl CommandNode {
+    pub const TYPE_MASK: Byte = 0x03;
+    pub const ROOT: Byte = 0;
+    pub const LITERAL: Byte = 1;
+    pub const ARGUMENT: Byte = 2;
+    pub const EXECUTABLE: Byte = 0x04;
+    pub const HAS_REDIRECT: Byte = 0x08;
+    pub const HAS_SUGGESTIONS_TYPE: Byte = 0x10;
+
+    pub fn node_type(&self) -> Byte {
+        self.flags & Self::TYPE_MASK
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.flags & Self::EXECUTABLE != 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct CommandParser {
+    pub identifier: Identifier,
+    #[declio(ctx(decode = "identifier.0 .0.as_str()"))]
+    pub properties: ParserProperties,
+}
+
+/// Properties of an argument parser, which depend on the parser identifier.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParserProperties {
+    None,
+    Double(ParserBounds<Double>),
+    Float(ParserBounds<Float>),
+    Integer(ParserBounds<Int>),
+    Long(ParserBounds<Long>),
+    /// 0 is a single word, 1 a quotable phrase and 2 the rest of the input.
+    String(VarInt),
+    Entity {
+        flags: Byte,
+    },
+    ScoreHolder {
+        flags: Byte,
+    },
+    Range {
+        decimals: Boolean,
+    },
+}
+
+impl ParserProperties {
+    pub const SINGLE_ENTITY: Byte = 0x01;
+    pub const ONLY_PLAYERS: Byte = 0x02;
+    pub const MULTIPLE_SCORE_HOLDERS: Byte = 0x01;
+}
+
+impl Encode for ParserProperties {
+    fn encode<W>(&self, _: (), writer: &mut W) -> Result<(), declio::Error>
+    where
+        W: io::Write,
+    {
+        match self {
+            Self::None => Ok(()),
+            Self::Double(bounds) => bounds.encode((), writer),
+            Self::Float(bounds) => bounds.encode((), writer),
+            Self::Integer(bounds) => bounds.encode((), writer),
+            Self::Long(bounds) => bounds.encode((), writer),
+            Self::String(behavior) => behavior.encode((), writer),
+            Self::Entity { flags } | Self::ScoreHolder { flags } => flags.encode((), writer),
+            Self::Range { decimals } => decimals.encode((), writer),
+        }
+    }
+}
+
+impl Decode<&str> for ParserProperties {
+    fn decode<R>(parser: &str, reader: &mut R) -> Result<Self, declio::Error>
+    where
+        R: io::Read,
+    {
+        Ok(match parser {
+            "brigadier:double" => Self::Double(ParserBounds::decode((), reader)?),
+            "brigadier:float" => Self::Float(ParserBounds::decode((), reader)?),
+            "brigadier:integer" => Self::Integer(ParserBounds::decode((), reader)?),
+            "brigadier:long" => Self::Long(ParserBounds::decode((), reader)?),
+            "brigadier:string" => Self::String(VarInt::decode((), reader)?),
+            "minecraft:entity" => Self::Entity {
+                flags: Byte::decode((), reader)?,
+            },
+            "minecraft:score_holder" => Self::ScoreHolder {
+                flags: Byte::decode((), reader)?,
+            },
+            "minecraft:range" => Self::Range {
+                decimals: Boolean::decode((), reader)?,
+            },
+            _ => Self::None,
+        })
+    }
+}
+
+/// Optional minimum and maximum of a numeric argument.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParserBounds<T> {
+    pub min: Option<T>,
+    pub max: Option<T>,
+}
+
+impl<T> ParserBounds<T> {
+    const HAS_MIN: Byte = 0x01;
+    const HAS_MAX: Byte = 0x02;
+}
+
+impl<T: Encode> Encode for ParserBounds<T> {
+    fn encode<W>(&self, _: (), writer: &mut W) -> Result<(), declio::Error>
+    where
+        W: io::Write,
+    {
+        let mut flags = 0;
+        if self.min.is_some() {
+            flags |= Self::HAS_MIN;
+        }
+        if self.max.is_some() {
+            flags |= Self::HAS_MAX;
+        }
+        flags.encode((), writer)?;
+        if let Some(min) = &self.min {
+            min.encode((), writer)?;
+        }
+        if let Some(max) = &self.max {
+            max.encode((), writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Decode> Decode for ParserBounds<T> {
+    fn decode<R>(_: (), reader: &mut R) -> Result<Self, declio::Error>
+    where
+        R: io::Read,
+    {
+        let flags = Byte::decode((), reader)?;
+        let min = if flags & Self::HAS_MIN != 0 {
+            Some(T::decode((), reader)?)
+        } else {
+            None
+        };
+        let max = if flags & Self::HAS_MAX != 0 {
+            Some(T::decode((), reader)?)
+        } else {
+            None
+        };
+        Ok(Self { min, max })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct Recipe {
+    pub type_: Identifier,
+    pub recipe_id: Identifier,
+    #[declio(ctx(decode = "type_.0 .0.as_str()"))]
+    pub data: RecipeData,
+}
+
+/// The items accepted in one ingredient slot of a recipe; empty for an unused slot.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct Ingredient(#[declio(with = "LengthPrefix::<VarInt>")] pub Vec<Slot>);
+
+impl Ingredient {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn matches(&self, item_id: i32) -> bool {
+        self.0.iter().any(|slot| match slot {
+            Slot::Present { item_id: id, .. } => id.0 == item_id,
+            Slot::NotPresent => false,
+        })
+    }
+}
+
+/// Contents of a recipe, which depend on the recipe type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecipeData {
+    CraftingShapeless {
+        group: String,
+        ingredients: Vec<Ingredient>,
+        result: Slot,
+    },
+    CraftingShaped {
+        width: VarInt,
+        height: VarInt,
+        group: String,
+        /// Row-major, `width * height` entries.
+        ingredients: Vec<Ingredient>,
+        result: Slot,
+    },
+    /// Smelting, blasting, smoking and campfire cooking.
+    Cooking {
+        group: String,
+        ingredient: Ingredient,
+        result: Slot,
+        experience: Float,
+        cooking_time: VarInt,
+    },
+    Stonecutting {
+        group: String,
+        ingredient: Ingredient,
+        result: Slot,
+    },
+    Smithing {
+        base: Ingredient,
+        addition: Ingredient,
+        result: Slot,
+    },
+    /// Recipes implemented in code, such as `crafting_special_armordye`, which have no data.
+    Special,
+}
+
+impl Encode for RecipeData {
+    fn encode<W>(&self, _: (), writer: &mut W) -> Result<(), declio::Error>
+    where
+        W: io::Write,
+    {
+        match self {
+            Self::CraftingShapeless {
+                group,
+                ingredients,
+                result,
+            } => {
+                group.encode((), writer)?;
+                LengthPrefix::<VarInt>::encode(ingredients, (), writer)?;
+                result.encode((), writer)
+            }
+            Self::CraftingShaped {
+                width,
+                height,
+                group,
+                ingredients,
+                result,
+            } => {
+                width.encode((), writer)?;
+                height.encode((), writer)?;
+                group.encode((), writer)?;
+                ingredients.encode((), writer)?;
+                result.encode((), writer)
+            }
+            Self::Cooking {
+                group,
+                ingredient,
+                result,
+                experience,
+                cooking_time,
+            } => {
+                group.encode((), writer)?;
+                ingredient.encode((), writer)?;
+                result.encode((), writer)?;
+                experience.encode((), writer)?;
+                cooking_time.encode((), writer)
+            }
+            Self::Stonecutting {
+                group,
+                ingredient,
+                result,
+            } => {
+                group.encode((), writer)?;
+                ingredient.encode((), writer)?;
+                result.encode((), writer)
+            }
+            Self::Smithing {
+                base,
+                addition,
+                result,
+            } => {
+                base.encode((), writer)?;
+                addition.encode((), writer)?;
+                result.encode((), writer)
+            }
+            Self::Special => Ok(()),
+        }
+    }
+}
+
+impl Decode<&str> for RecipeData {
+    fn decode<R>(type_: &str, reader: &mut R) -> Result<Self, declio::Error>
+    where
+        R: io::Read,
+    {
+        let type_ = type_.strip_prefix("minecraft:").unwrap_or(type_);
+        Ok(match type_ {
+            "crafting_shapeless" => Self::CraftingShapeless {
+                group: String::decode((), reader)?,
+                ingredients: LengthPrefix::<VarInt>::decode((), reader)?,
+                result: Slot::decode((), reader)?,
+            },
+            "crafting_shaped" => {
+                let width = VarInt::decode((), reader)?;
+                let height = VarInt::decode((), reader)?;
+                let len = Len::try_from(width.0.saturating_mul(height.0))?;
+                Self::CraftingShaped {
+                    width,
+                    height,
+                    group: String::decode((), reader)?,
+                    ingredients: Vec::decode(len, reader)?,
+                    result: Slot::decode((), reader)?,
+                }
+            }
+            "smelting" | "blasting" | "smoking" | "campfire_cooking" => Self::Cooking {
+                group: String::decode((), reader)?,
+                ingredient: Ingredient::decode((), reader)?,
+                result: Slot::decode((), reader)?,
+                experience: Float::decode((), reader)?,
+                cooking_time: VarInt::decode((), reader)?,
+            },
+            "stonecutting" => Self::Stonecutting {
+                group: String::decode((), reader)?,
+                ingredient: Ingredient::decode((), reader)?,
+                result: Slot::decode((), reader)?,
+            },
+            "smithing" => Self::Smithing {
+                base: Ingredient::decode((), reader)?,
+                addition: Ingredient::decode((), reader)?,
+                result: Slot::decode((), reader)?,
+            },
+            _ if type_.starts_with("crafting_special_") => Self::Special,
+            _ => return Err(declio::Error::new("unknown recipe type")),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct AdvancementEntry {
+    pub id: Identifier,
+    pub advancement: Advancement,
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct Advancement {
+    pub has_parent: Boolean,
+    #[declio(skip_if = "!has_parent")]
+    pub parent_id: Option<Identifier>,
+    pub has_display: Boolean,
+    #[declio(skip_if = "!has_display")]
+    pub display: Option<AdvancementDisplay>,
+    #[declio(with = "LengthPrefix::<VarInt>")]
+    pub criteria: Vec<Identifier>,
+    /// The advancement is complete when every requirement has at least one achieved criterion.
+    #[declio(with = "LengthPrefix::<VarInt>")]
+    pub requirements: Vec<AdvancementRequirement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct AdvancementRequirement(#[declio(with = "LengthPrefix::<VarInt>")] pub Vec<String>);
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct AdvancementDisplay {
+    pub title: Chat,
+    pub description: Chat,
+    pub icon: Slot,
+    pub frame_type: VarInt,
+    pub flags: Int,
+    #[declio(skip_if = "*flags & Self::HAS_BACKGROUND_TEXTURE == 0")]
+    pub background_texture: Option<Identifier>,
+    pub x: Float,
+    pub y: Float,
+}
+
+impl AdvancementDisplay {
+    pub const TASK: VarInt = VarInt(0);
+    pub const CHALLENGE: VarInt = VarInt(1);
+    pub const GOAL: VarInt = VarInt(2);
+
+    pub const HAS_BACKGROUND_TEXTURE: Int = 0x01;
+    pub const SHOW_TOAST: Int = 0x02;
+    pub const HIDDEN: Int = 0x04;
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct AdvancementProgress {
+    pub id: Identifier,
+    #[declio(with = "LengthPrefix::<VarInt>")]
+    pub criteria: Vec<CriterionProgress>,
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct CriterionProgress {
+    pub id: Identifier,
+    pub achieved: Boolean,
+    /// Milliseconds since the Unix epoch.
+    #[declio(skip_if = "!achieved")]
+    pub date_of_achieving: Option<Long>,
+}
+
+/// Extra data of a particle, which depends on the particle ID.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParticleData {
+    None,
+    Block {
+        block_state: VarInt,
+    },
+    Dust {
+        red: Float,
+        green: Float,
+        blue: Float,
+        scale: Float,
+    },
+    FallingDust {
+        block_state: VarInt,
+    },
+    Item {
+        item: Slot,
+    },
+}
+
+impl ParticleData {
+    pub const BLOCK: Int = 3;
+    pub const DUST: Int = 14;
+    pub const FALLING_DUST: Int = 23;
+    pub const ITEM: Int = 34;
+}
+
+impl Encode for ParticleData {
+    fn encode<W>(&self, _: (), writer: &mut W) -> Result<(), declio::Error>
+    where
+        W: io::Write,
+    {
+        match self {
+            Self::None => Ok(()),
+            Self::Block { block_state } | Self::FallingDust { block_state } => {
+                block_state.encode((), writer)
+            }
+            Self::Dust {
+                red,
+                green,
+                blue,
+                scale,
+            } => {
+                red.encode((), writer)?;
+                green.encode((), writer)?;
+                blue.encode((), writer)?;
+                scale.encode((), writer)
+            }
+            Self::Item { item } => item.encode((), writer),
+        }
+    }
+}
+
+impl Decode<Int> for ParticleData {
+    fn decode<R>(particle_id: Int, reader: &mut R) -> Result<Self, declio::Error>
+    where
+        R: io::Read,
+    {
+        Ok(match particle_id {
+            Self::BLOCK => Self::Block {
+                block_state: VarInt::decode((), reader)?,
+            },
+            Self::DUST => Self::Dust {
+                red: Float::decode((), reader)?,
+                green: Float::decode((), reader)?,
+                blue: Float::decode((), reader)?,
+                scale: Float::decode((), reader)?,
+            },
+            Self::FALLING_DUST => Self::FallingDust {
+                block_state: VarInt::decode((), reader)?,
+            },
+            Self::ITEM => Self::Item {
+                item: Slot::decode((), reader)?,
+            },
+            _ => Self::None,
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Encode, Decode)]
 #[declio(id_type = "VarInt")]
 pub enum InteractEntityAction {
@@ -1298,3 +2041,153 @@ pub enum InteractEntityAction {
         hand: VarInt,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::Value;
+
+    #[test]
+    fn entity_metadata() {
+        let bytes = vec![
+            0x44, // packet id
+            0x2a, // entity id
+            0x02, 0x05, 0x01, 0x05, b'"', b'B', b'o', b'b', b'"', // custom name
+            0x06, 0x12, 0x05, // pose: sneaking
+            0x08, 0x02, 0x41, 0xa0, 0x00, 0x00, // health: 20.0
+            0x09, 0x0f, 0x0e, 0x3f, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x3f, 0x80, 0x00, 0x00, // particle: red dust
+            0xff,
+        ];
+        let packet = Clientbound::EntityMetadata {
+            entity_id: VarInt(42),
+            metadata: vec![
+                MetadataEntry {
+                    index: 2,
+                    value: MetadataValue::OptChat {
+                        present: true,
+                        value: Some(Chat(serde_json::json!("Bob"))),
+                    },
+                },
+                MetadataEntry {
+                    index: 6,
+                    value: MetadataValue::Pose(VarInt(5)),
+                },
+                MetadataEntry {
+                    index: 8,
+                    value: MetadataValue::Float(20.0),
+                },
+                MetadataEntry {
+                    index: 9,
+                    value: MetadataValue::Particle {
+                        particle_id: VarInt(ParticleData::DUST),
+                        data: ParticleData::Dust {
+                            red: 1.0,
+                            green: 0.0,
+                            blue: 0.0,
+                            scale: 1.0,
+                        },
+                    },
+                },
+            ],
+        };
+
+        let mut input = bytes.as_slice();
+        assert_eq!(Clientbound::decode((), &mut input).unwrap(), packet);
+        assert!(input.is_empty());
+
+        let mut output = Vec::new();
+        packet.encode((), &mut output).unwrap();
+        assert_eq!(output, bytes);
+    }
+
+    #[test]
+    fn property_final_value() {
+        let modifier = |amount, operation| PropertyModifier {
+            uuid: Uuid(0),
+            amount,
+            operation,
+        };
+        let property = EntityProperty {
+            key: Identifier("generic.movement_speed".to_string().into()),
+            value: 0.1,
+            modifiers: vec![
+                modifier(0.1, PropertyModifier::ADD),
+                modifier(0.5, PropertyModifier::MULTIPLY_BASE),
+                modifier(0.5, PropertyModifier::MULTIPLY_BASE),
+                modifier(1.0, PropertyModifier::MULTIPLY_TOTAL),
+            ],
+        };
+        assert!((property.final_value() - 0.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn particle() {
+        let packet = Clientbound::Particle {
+            particle_id: ParticleData::DUST,
+            long_distance: false,
+            x: 1.0,
+            y: 64.0,
+            z: -1.0,
+            offset_x: 0.0,
+            offset_y: 0.5,
+            offset_z: 0.0,
+            particle_data: 0.0,
+            particle_count: 8,
+            data: ParticleData::Dust {
+                red: 1.0,
+                green: 0.0,
+                blue: 0.0,
+                scale: 1.0,
+            },
+        };
+        let mut bytes = Vec::new();
+        packet.encode((), &mut bytes).unwrap();
+        let mut input = bytes.as_slice();
+        assert_eq!(Clientbound::decode((), &mut input).unwrap(), packet);
+        assert!(input.is_empty());
+
+        // Particles without extra data read and write nothing.
+        let mut bytes = Vec::new();
+        ParticleData::None.encode((), &mut bytes).unwrap();
+        assert!(bytes.is_empty());
+        let mut input: &[u8] = &[0x01];
+        assert_eq!(
+            ParticleData::decode(0, &mut input).unwrap(),
+            ParticleData::None
+        );
+        assert_eq!(input.len(), 1);
+    }
+
+    #[test]
+    fn entity_equipment() {
+        let bytes = vec![
+            0x47, // packet id
+            0x07, // entity id
+            0x80, 0x01, 0x99, 0x05, 0x01, 0x00, // main hand: 1 item 665, no NBT
+            0x05, 0x00, // helmet: empty
+        ];
+        let packet = Clientbound::EntityEquipment {
+            entity_id: VarInt(7),
+            equipment: vec![
+                (
+                    EquipmentSlot::MainHand,
+                    Slot::Present {
+                        item_id: VarInt(665),
+                        item_count: 1,
+                        nbt: Nbt::new(std::string::String::new(), Value::End),
+                    },
+                ),
+                (EquipmentSlot::Helmet, Slot::NotPresent),
+            ],
+        };
+
+        let mut input = bytes.as_slice();
+        assert_eq!(Clientbound::decode((), &mut input).unwrap(), packet);
+        assert!(input.is_empty());
+
+        let mut output = Vec::new();
+        packet.encode((), &mut output).unwrap();
+        assert_eq!(output, bytes);
+    }
+}
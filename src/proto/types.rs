@@ -213,7 +213,7 @@ pub enum Slot {
     NotPresent,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Position {
     pub x: i32,
     pub z: i32,
@@ -267,10 +267,21 @@ impl Decode for Position {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
 pub struct Angle(pub u8);
 
-#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+impl Angle {
+    pub fn from_degrees(degrees: f32) -> Self {
+        Self((degrees.rem_euclid(360.0) * 256.0 / 360.0) as u8)
+    }
+
+    /// The angle in degrees, in the range `-180.0..180.0`.
+    pub fn degrees(&self) -> f32 {
+        self.0 as i8 as f32 * 360.0 / 256.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Encode, Decode)]
 pub struct Uuid(pub u128);
 
 #[cfg(test)]
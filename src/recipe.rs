@@ -0,0 +1,239 @@
+use crate::proto::play::{Ingredient, Recipe, RecipeData};
+use crate::proto::types::{Identifier, Slot};
+use std::collections::{HashMap, HashSet};
+
+// Actions of the `UnlockRecipes` packet.
+pub const UNLOCK_INIT: i32 = 0;
+pub const UNLOCK_ADD: i32 = 1;
+pub const UNLOCK_REMOVE: i32 = 2;
+
+impl Recipe {
+    pub fn id(&self) -> &str {
+        &self.recipe_id.0 .0
+    }
+
+    /// The ingredients of the recipe, excluding unused slots of shaped recipes.
+    pub fn ingredients(&self) -> Vec<&Ingredient> {
+        let ingredients: Vec<&Ingredient> = match &self.data {
+            RecipeData::CraftingShapeless { ingredients, .. }
+            | RecipeData::CraftingShaped { ingredients, .. } => ingredients.iter().collect(),
+            RecipeData::Cooking { ingredient, .. }
+            | RecipeData::Stonecutting { ingredient, .. } => {
+                vec![ingredient]
+            }
+            RecipeData::Smithing { base, addition, .. } => vec![base, addition],
+            RecipeData::Special => Vec::new(),
+        };
+        ingredients
+            .into_iter()
+            .filter(|ingredient| !ingredient.is_empty())
+            .collect()
+    }
+
+    pub fn result(&self) -> Option<&Slot> {
+        match &self.data {
+            RecipeData::CraftingShapeless { result, .. }
+            | RecipeData::CraftingShaped { result, .. }
+            | RecipeData::Cooking { result, .. }
+            | RecipeData::Stonecutting { result, .. }
+            | RecipeData::Smithing { result, .. } => Some(result),
+            RecipeData::Special => None,
+        }
+    }
+
+    /// Whether one batch of this recipe can be made from the given items.
+    ///
+    /// Special recipes are never reported as craftable, since their ingredients are not declared.
+    pub fn can_make_from<'a, I>(&self, items: I) -> bool
+    where
+        I: IntoIterator<Item = &'a Slot>,
+    {
+        if let RecipeData::Special = self.data {
+            return false;
+        }
+        let mut available: HashMap<i32, i32> = HashMap::new();
+        for slot in items {
+            if let Slot::Present {
+                item_id,
+                item_count,
+                ..
+            } = slot
+            {
+                *available.entry(item_id.0).or_default() += *item_count as i32;
+            }
+        }
+
+        // Fill the most constrained ingredients first.
+        let mut ingredients = self.ingredients();
+        ingredients.sort_by_key(|ingredient| ingredient.0.len());
+        ingredients.into_iter().all(|ingredient| {
+            let found = available
+                .iter_mut()
+                .filter(|(&item_id, count)| **count > 0 && ingredient.matches(item_id))
+                .max_by_key(|(_, count)| **count);
+            match found {
+                Some((_, count)) => {
+                    *count -= 1;
+                    true
+                }
+                None => false,
+            }
+        })
+    }
+}
+
+/// The recipes declared by the server and the ones unlocked in the player's recipe book.
+#[derive(Debug, Clone, Default)]
+pub struct RecipeBook {
+    recipes: HashMap<String, Recipe>,
+    unlocked: HashSet<String>,
+    /// Recipes marked as new in the recipe book.
+    highlighted: HashSet<String>,
+}
+
+impl RecipeBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Recipe> {
+        self.recipes.get(id)
+    }
+
+    pub fn recipes(&self) -> impl Iterator<Item = &Recipe> {
+        self.recipes.values()
+    }
+
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.contains(id)
+    }
+
+    /// Whether a recipe is marked as new, because it was unlocked since the player last looked
+    /// at it.
+    pub fn is_highlighted(&self, id: &str) -> bool {
+        self.highlighted.contains(id)
+    }
+
+    /// Recipes that are both declared and unlocked.
+    pub fn unlocked(&self) -> impl Iterator<Item = &Recipe> {
+        self.unlocked
+            .iter()
+            .filter_map(move |id| self.recipes.get(id))
+    }
+
+    /// Unlocked recipes that can be made from the given items, such as the player's inventory.
+    pub fn craftable<'a>(&'a self, items: &'a [Slot]) -> impl Iterator<Item = &'a Recipe> {
+        self.unlocked()
+            .filter(move |recipe| recipe.can_make_from(items))
+    }
+
+    /// Replaces the declared recipes with the contents of a `DeclareRecipes` packet.
+    pub fn declare(&mut self, recipes: &[Recipe]) {
+        self.recipes = recipes
+            .iter()
+            .map(|recipe| (recipe.id().to_string(), recipe.clone()))
+            .collect();
+    }
+
+    /// Applies an `UnlockRecipes` packet.
+    ///
+    /// `displayed` is only used when initializing, and lists the unlocked recipes that are
+    /// marked as new.
+    pub fn unlock(&mut self, action: i32, recipe_ids: &[Identifier], displayed: &[Identifier]) {
+        let ids = |list: &[Identifier]| list.iter().map(|id| id.0 .0.clone()).collect::<Vec<_>>();
+        match action {
+            UNLOCK_INIT => {
+                self.unlocked = ids(recipe_ids).into_iter().collect();
+                self.highlighted = ids(displayed).into_iter().collect();
+            }
+            UNLOCK_ADD => {
+                self.unlocked.extend(ids(recipe_ids));
+                self.highlighted.extend(ids(recipe_ids));
+            }
+            UNLOCK_REMOVE => {
+                for id in ids(recipe_ids) {
+                    self.unlocked.remove(&id);
+                    self.highlighted.remove(&id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.recipes.clear();
+        self.unlocked.clear();
+        self.highlighted.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::play::Clientbound;
+    use crate::proto::types::VarInt;
+    use crate::test_util::{identifier, item};
+    use declio::{Decode, Encode};
+
+    #[test]
+    fn recipe_book() {
+        const PLANKS: i32 = 23;
+        const STICK: i32 = 600;
+        let sticks = Recipe {
+            type_: identifier("minecraft:crafting_shaped"),
+            recipe_id: identifier("minecraft:stick"),
+            data: RecipeData::CraftingShaped {
+                width: VarInt(1),
+                height: VarInt(2),
+                group: "sticks".to_string().into(),
+                ingredients: vec![
+                    Ingredient(vec![item(PLANKS, 1), item(PLANKS + 1, 1)]),
+                    Ingredient(vec![item(PLANKS, 1), item(PLANKS + 1, 1)]),
+                ],
+                result: item(STICK, 4),
+            },
+        };
+        let dye = Recipe {
+            type_: identifier("minecraft:crafting_special_armordye"),
+            recipe_id: identifier("minecraft:armor_dye"),
+            data: RecipeData::Special,
+        };
+
+        let packet = Clientbound::DeclareRecipes {
+            recipes: vec![sticks.clone(), dye],
+        };
+        let mut bytes = Vec::new();
+        packet.encode((), &mut bytes).unwrap();
+        let decoded = Clientbound::decode((), &mut bytes.as_slice()).unwrap();
+        assert_eq!(decoded, packet);
+
+        let mut book = RecipeBook::new();
+        if let Clientbound::DeclareRecipes { recipes } = decoded {
+            book.declare(&recipes);
+        }
+        assert_eq!(book.get("minecraft:stick"), Some(&sticks));
+        assert_eq!(book.unlocked().count(), 0);
+
+        // The second list only marks recipes as new, without unlocking them.
+        book.unlock(
+            UNLOCK_INIT,
+            &[identifier("minecraft:stick")],
+            &[identifier("minecraft:armor_dye")],
+        );
+        assert!(book.is_unlocked("minecraft:stick"));
+        assert!(!book.is_highlighted("minecraft:stick"));
+        assert!(!book.is_unlocked("minecraft:armor_dye"));
+
+        book.unlock(UNLOCK_ADD, &[identifier("minecraft:armor_dye")], &[]);
+        assert!(book.is_unlocked("minecraft:armor_dye"));
+        assert!(book.is_highlighted("minecraft:armor_dye"));
+
+        let inventory = [item(PLANKS, 1), item(PLANKS + 1, 1)];
+        let craftable: Vec<_> = book.craftable(&inventory).map(Recipe::id).collect();
+        assert_eq!(craftable, vec!["minecraft:stick"]);
+        assert_eq!(book.craftable(&inventory[..1]).count(), 0);
+
+        book.unlock(UNLOCK_REMOVE, &[identifier("minecraft:stick")], &[]);
+        assert_eq!(book.craftable(&inventory).count(), 0);
+    }
+}
@@ -0,0 +1,258 @@
+//! Async versions of the connection states, over `AsyncTransportSession`.
+//!
+//! The game state of `AsyncPlay` is a regular `Play` whose packets are written to a buffer, so
+//! everything it tracks is shared with the blocking client.
+
+use crate::auth::{AuthService, Authentication, SessionService, AUTH_SERVER, SESSION_SERVER};
+use crate::proto::handshake::{NextState, Serverbound as HandshakeServerbound};
+use crate::proto::status::{
+    Clientbound as StatusClientbound, Serverbound as StatusServerbound, StatusData,
+};
+use crate::proto::types::Long;
+use crate::proto::{self, login, AsyncTransportSession, FrameReader};
+use crate::state::login::Encryption;
+use crate::state::{Event, Play};
+use std::convert::TryInto;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+
+pub async fn connect_async(
+    host: String,
+    port: u16,
+    version: i32,
+) -> anyhow::Result<AsyncHandshake> {
+    Ok(AsyncHandshake::new(
+        AsyncTransportSession::connect(host.as_str(), port).await?,
+        host,
+        port,
+        version,
+    ))
+}
+
+pub struct AsyncHandshake<R = OwnedReadHalf, W = OwnedWriteHalf> {
+    session: AsyncTransportSession<R, W>,
+    host: String,
+    port: u16,
+    version: i32,
+}
+
+impl<R, W> AsyncHandshake<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    pub fn new(
+        session: AsyncTransportSession<R, W>,
+        host: String,
+        port: u16,
+        version: i32,
+    ) -> Self {
+        Self {
+            session,
+            host,
+            port,
+            version,
+        }
+    }
+
+    pub async fn status(self) -> anyhow::Result<AsyncStatus<R, W>> {
+        Ok(AsyncStatus::new(self.handshake(NextState::Status).await?))
+    }
+
+    pub async fn login(self) -> anyhow::Result<AsyncLogin<R, W>> {
+        Ok(AsyncLogin::new(self.handshake(NextState::Login).await?))
+    }
+
+    async fn handshake(
+        mut self,
+        next_state: NextState,
+    ) -> anyhow::Result<AsyncTransportSession<R, W>> {
+        self.session
+            .write_packet(&HandshakeServerbound::Handshake {
+                protocol_version: self.version.into(),
+                server_address: self.host.into(),
+                server_port: self.port,
+                next_state,
+            })
+            .await?;
+        Ok(self.session)
+    }
+}
+
+pub struct AsyncStatus<R = OwnedReadHalf, W = OwnedWriteHalf> {
+    session: AsyncTransportSession<R, W>,
+}
+
+impl<R, W> AsyncStatus<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    pub fn new(session: AsyncTransportSession<R, W>) -> Self {
+        Self { session }
+    }
+
+    pub async fn query(mut self) -> anyhow::Result<(StatusData, Duration)> {
+        let start = Instant::now();
+        let timestamp = UNIX_EPOCH.elapsed().expect("timestamp error").as_millis() as Long;
+        self.session.queue_packet(&StatusServerbound::Request)?;
+        self.session
+            .queue_packet(&StatusServerbound::Ping { payload: timestamp })?;
+        self.session.flush().await?;
+
+        let data = match self.session.read_packet().await? {
+            StatusClientbound::Response { data } => data,
+            _ => return Err(anyhow::Error::msg("unexpected packet from server")),
+        };
+        match self.session.read_packet().await? {
+            StatusClientbound::Pong { payload } => {
+                if payload != timestamp {
+                    return Err(anyhow::Error::msg("ping mismatch"));
+                }
+            }
+            _ => return Err(anyhow::Error::msg("unexpected packet from server")),
+        }
+        Ok((data, start.elapsed()))
+    }
+}
+
+pub struct AsyncLogin<R = OwnedReadHalf, W = OwnedWriteHalf> {
+    session: AsyncTransportSession<R, W>,
+}
+
+impl<R, W> AsyncLogin<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    pub fn new(session: AsyncTransportSession<R, W>) -> Self {
+        Self { session }
+    }
+
+    /// Logs in, refreshing the access token first if the server is in online mode and the
+    /// token is no longer valid.
+    pub async fn login(self, auth: &mut Authentication) -> anyhow::Result<AsyncPlay<R, W>> {
+        let client = reqwest::Client::new();
+        self.login_with(
+            auth,
+            &AuthService::with_client(AUTH_SERVER, client.clone()),
+            &SessionService::with_client(SESSION_SERVER, client),
+        )
+        .await
+    }
+
+    /// Logs in like `login`, through the given authentication and session servers.
+    pub async fn login_with(
+        mut self,
+        auth: &mut Authentication,
+        auth_service: &AuthService<reqwest::Client>,
+        session_service: &SessionService<reqwest::Client>,
+    ) -> anyhow::Result<AsyncPlay<R, W>> {
+        self.session
+            .write_packet(&login::Serverbound::LoginStart {
+                name: auth.name().to_string().into(),
+            })
+            .await?;
+
+        loop {
+            match self.session.read_packet().await? {
+                login::Clientbound::Disconnect { reason } => {
+                    return Err(anyhow::Error::msg(format!("disconnected: {:?}", reason)));
+                }
+                login::Clientbound::EncryptionRequest {
+                    server_id,
+                    public_key_der,
+                    verify_token,
+                } => {
+                    let encryption = Encryption::new(&server_id.0, &public_key_der, &verify_token)?;
+                    auth_service.ensure_valid(auth).await?;
+                    session_service.join(auth, &encryption.server_hash).await?;
+
+                    self.session.write_packet(&encryption.response).await?;
+                    self.session.enable_encryption(encryption.shared_secret)?;
+                }
+                login::Clientbound::LoginSuccess { uuid, username } => {
+                    let (reader, writer, encoder) = self.session.into_parts();
+                    return Ok(AsyncPlay {
+                        reader,
+                        writer,
+                        play: Play::new(encoder, uuid, username.into()),
+                        pending: None,
+                    });
+                }
+                login::Clientbound::SetCompression { threshold } => {
+                    let threshold = if threshold.0 < 0 {
+                        None
+                    } else {
+                        Some(threshold.0.try_into()?)
+                    };
+                    self.session.set_compression_threshold(threshold);
+                }
+                login::Clientbound::LoginPluginRequest { message_id, .. } => {
+                    self.session
+                        .write_packet(&login::Serverbound::LoginPluginResponse {
+                            message_id,
+                            success: false,
+                            data: vec![],
+                        })
+                        .await?;
+                }
+            }
+        }
+    }
+}
+
+/// The play state over an async connection.
+///
+/// Actions on the inner `Play`, such as `send_chat` or `tick`, only queue their packets; they
+/// are sent by the next `flush`, `poll` or `tick`.
+pub struct AsyncPlay<R = OwnedReadHalf, W = OwnedWriteHalf> {
+    reader: FrameReader<R>,
+    writer: W,
+    play: Play<std::io::Empty, Vec<u8>>,
+    /// An event whose packet was handled but not returned yet, because `poll` was cancelled
+    /// while flushing.
+    pending: Option<Event>,
+}
+
+impl<R, W> AsyncPlay<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    pub fn play(&self) -> &Play<std::io::Empty, Vec<u8>> {
+        &self.play
+    }
+
+    /// The game state. Its `poll` and `try_poll` must not be used, since it has no connection
+    /// to read from.
+    pub fn play_mut(&mut self) -> &mut Play<std::io::Empty, Vec<u8>> {
+        &mut self.play
+    }
+
+    /// Sends all queued packets.
+    pub async fn flush(&mut self) -> anyhow::Result<()> {
+        proto::flush(self.play.session_mut(), &mut self.writer).await
+    }
+
+    /// Runs one tick of movement and sends the resulting position.
+    pub async fn tick(&mut self) -> anyhow::Result<()> {
+        self.play.tick()?;
+        self.flush().await
+    }
+
+    /// Handles packets until one of them produces an event.
+    ///
+    /// This is cancel safe, so it can be raced against a tick interval with `tokio::select!`.
+    pub async fn poll(&mut self) -> anyhow::Result<Event> {
+        loop {
+            self.flush().await?;
+            if let Some(event) = self.pending.take() {
+                return Ok(event);
+            }
+            let frame = self.reader.read_frame().await?;
+            self.pending = self.play.handle_frame(frame)?;
+        }
+    }
+}
@@ -1,13 +1,10 @@
-use crate::auth::Authentication;
+use crate::auth::{self, AuthService, Authentication, SessionService};
 use crate::proto::login::{Clientbound, Serverbound};
 use crate::proto::TransportSession;
 use crate::state::Play;
 use anyhow::Context;
 use rsa::{PaddingScheme, PublicKey, RSAPublicKey};
-use serde_json::json;
-use sha1::{Digest, Sha1};
 use std::convert::TryInto;
-use std::fmt::Write;
 use std::io;
 use std::net::TcpStream;
 
@@ -24,7 +21,19 @@ where
         Self { session }
     }
 
-    pub fn login(mut self, auth: &Authentication) -> anyhow::Result<Play<R, W>> {
+    /// Logs in, refreshing the access token first if the server is in online mode and the
+    /// token is no longer valid.
+    pub fn login(self, auth: &mut Authentication) -> anyhow::Result<Play<R, W>> {
+        self.login_with(auth, &AuthService::default(), &SessionService::default())
+    }
+
+    /// Logs in like `login`, through the given authentication and session servers.
+    pub fn login_with(
+        mut self,
+        auth: &mut Authentication,
+        auth_service: &AuthService,
+        session_service: &SessionService,
+    ) -> anyhow::Result<Play<R, W>> {
         self.session.write_packet(&Serverbound::LoginStart {
             name: auth.name().to_string().into(),
         })?;
@@ -39,66 +48,12 @@ where
                     public_key_der,
                     verify_token,
                 } => {
-                    let public_key = RSAPublicKey::from_pkcs8(&public_key_der)
-                        .context("received bad key from server")?;
-                    let shared_secret: [u8; 16] = rand::random();
-
-                    let shared_secret_encrypted = public_key.encrypt(
-                        &mut rand::thread_rng(),
-                        PaddingScheme::PKCS1v15Encrypt,
-                        &shared_secret,
-                    )?;
-                    let verify_token_encrypted = public_key.encrypt(
-                        &mut rand::thread_rng(),
-                        PaddingScheme::PKCS1v15Encrypt,
-                        &verify_token,
-                    )?;
-
-                    let mut hash = Sha1::new()
-                        .chain(&server_id.0)
-                        .chain(&shared_secret)
-                        .chain(&public_key_der)
-                        .finalize();
-
-                    let mut hexdigest = String::with_capacity(41);
-                    // twos complement hexdigest, because why not?
-                    // let's make this protocol as convoluted as possible.
-                    if hash[0] & 0x80 != 0 {
-                        hexdigest.push('-');
-                        let mut carry = true;
-                        for byte in hash.as_mut_slice().iter_mut().rev() {
-                            *byte = !*byte;
-                            if carry {
-                                let (next_byte, next_carry) = byte.overflowing_add(1);
-                                *byte = next_byte;
-                                carry = next_carry;
-                            }
-                        }
-                    }
-                    for byte in hash.as_slice() {
-                        write!(hexdigest, "{:02x}", byte).unwrap();
-                    }
-
-                    let client = reqwest::blocking::Client::new();
-                    let response = client
-                        .post("https://sessionserver.mojang.com/session/minecraft/join")
-                        .json(&json!({
-                            "accessToken": auth.access_token(),
-                            "selectedProfile": auth.uuid(),
-                            "serverId": hexdigest,
-                        }))
-                        .send()?;
-                    response
-                        .error_for_status_ref()
-                        .context("session server error")?;
-
-                    self.session
-                        .write_packet(&Serverbound::EncryptionResponse {
-                            shared_secret: shared_secret_encrypted,
-                            verify_token: verify_token_encrypted,
-                        })?;
+                    let encryption = Encryption::new(&server_id.0, &public_key_der, &verify_token)?;
+                    auth_service.ensure_valid(auth)?;
+                    session_service.join(auth, &encryption.server_hash)?;
 
-                    self.session.enable_encryption(shared_secret)?;
+                    self.session.write_packet(&encryption.response)?;
+                    self.session.enable_encryption(encryption.shared_secret)?;
                 }
                 Clientbound::LoginSuccess { uuid, username } => {
                     return Ok(Play::new(self.session, uuid, username.into()));
@@ -123,3 +78,44 @@ where
         }
     }
 }
+
+/// The client's side of enabling encryption.
+pub(crate) struct Encryption {
+    pub shared_secret: [u8; 16],
+    /// The `EncryptionResponse` to send, before enabling encryption.
+    pub response: Serverbound,
+    pub server_hash: String,
+}
+
+impl Encryption {
+    /// Generates a shared secret and encrypts it and the verify token with the server's key.
+    pub fn new(
+        server_id: &str,
+        public_key_der: &[u8],
+        verify_token: &[u8],
+    ) -> anyhow::Result<Self> {
+        let public_key =
+            RSAPublicKey::from_pkcs8(public_key_der).context("received bad key from server")?;
+        let shared_secret: [u8; 16] = rand::random();
+
+        let shared_secret_encrypted = public_key.encrypt(
+            &mut rand::thread_rng(),
+            PaddingScheme::PKCS1v15Encrypt,
+            &shared_secret,
+        )?;
+        let verify_token_encrypted = public_key.encrypt(
+            &mut rand::thread_rng(),
+            PaddingScheme::PKCS1v15Encrypt,
+            verify_token,
+        )?;
+
+        Ok(Self {
+            shared_secret,
+            response: Serverbound::EncryptionResponse {
+                shared_secret: shared_secret_encrypted,
+                verify_token: verify_token_encrypted,
+            },
+            server_hash: auth::server_hash(server_id, &shared_secret, public_key_der),
+        })
+    }
+}
@@ -1,12 +1,20 @@
+#[cfg(feature = "async")]
+mod async_state;
 mod handshake;
 mod login;
 mod play;
+mod server_login;
 mod status;
+mod tick;
 
+#[cfg(feature = "async")]
+pub use self::async_state::{connect_async, AsyncHandshake, AsyncLogin, AsyncPlay, AsyncStatus};
 pub use self::handshake::Handshake;
 pub use self::login::Login;
-pub use self::play::Play;
+pub use self::play::{Event, Play};
+pub use self::server_login::{ServerKey, ServerLogin};
 pub use self::status::Status;
+pub use self::tick::{TickLoop, TICK_DURATION};
 
 use crate::proto::TransportSession;
 
@@ -1,6 +1,17 @@
-use crate::proto::play::{Clientbound, Gamemode, Serverbound};
-use crate::proto::types::Uuid;
-use crate::proto::{Peekable, TransportSession};
+use crate::advancement::AdvancementTracker;
+use crate::command::CommandGraph;
+use crate::entity::{self, Entity, EntityTracker};
+use crate::inventory::Inventory;
+use crate::pathfind::{self, Path, PathConfig, PathFollower};
+use crate::physics::{MovementInput, PlayerPhysics};
+use crate::proto::play::{Clientbound, CombatEvent, EntityProperty, Gamemode, Serverbound};
+use crate::proto::types::{Chat, Position, Slot, Uuid};
+use crate::proto::{PacketFrame, Peekable, TransportSession};
+use crate::recipe::RecipeBook;
+use crate::world::World;
+use anyhow::Context;
+use std::collections::HashMap;
+use std::fmt;
 use std::io;
 use std::net::TcpStream;
 
@@ -14,12 +25,27 @@ pub struct Play<R = TcpStream, W = TcpStream> {
     view_distance: i32,
     enable_respawn_screen: bool,
     held_item: i8,
-    x: f64,
-    y: f64,
-    z: f64,
-    yaw: f32,
-    pitch: f32,
-    on_ground: bool,
+    health: f32,
+    food: i32,
+    food_saturation: f32,
+
+    physics: PlayerPhysics,
+    input: MovementInput,
+    position_known: bool,
+    last_sent: LastSent,
+    follower: Option<PathFollower>,
+
+    properties: HashMap<String, EntityProperty>,
+
+    world: World,
+    entities: EntityTracker,
+    inventory: Inventory,
+    commands: CommandGraph,
+    recipes: RecipeBook,
+    advancements: AdvancementTracker,
+
+    /// Number of packets that could not be decoded, by packet ID.
+    skipped_packets: HashMap<i32, u64>,
 }
 
 impl<R, W> Play<R, W>
@@ -38,13 +64,326 @@ where
             view_distance: -1,
             enable_respawn_screen: true,
             held_item: 0,
-            x: 0.0,
-            y: 0.0,
-            z: 0.0,
-            yaw: 0.0,
-            pitch: 0.0,
-            on_ground: true,
+            health: 20.0,
+            food: 20,
+            food_saturation: 5.0,
+
+            physics: PlayerPhysics::new(),
+            input: MovementInput::default(),
+            position_known: false,
+            last_sent: LastSent::default(),
+            follower: None,
+
+            properties: HashMap::new(),
+
+            world: World::new(),
+            entities: EntityTracker::new(),
+            inventory: Inventory::new(),
+            commands: CommandGraph::default(),
+            recipes: RecipeBook::new(),
+            advancements: AdvancementTracker::new(),
+
+            skipped_packets: HashMap::new(),
+        }
+    }
+
+    pub fn uuid(&self) -> &Uuid {
+        &self.uuid
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn entity_id(&self) -> i32 {
+        self.entity_id
+    }
+
+    pub fn gamemode(&self) -> Gamemode {
+        self.gamemode
+    }
+
+    pub fn held_item(&self) -> i8 {
+        self.held_item
+    }
+
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+
+    pub fn food(&self) -> i32 {
+        self.food
+    }
+
+    pub fn position(&self) -> (f64, f64, f64) {
+        self.physics.position()
+    }
+
+    pub fn rotation(&self) -> (f32, f32) {
+        (self.physics.yaw, self.physics.pitch)
+    }
+
+    pub fn physics(&self) -> &PlayerPhysics {
+        &self.physics
+    }
+
+    pub fn input(&self) -> &MovementInput {
+        &self.input
+    }
+
+    /// Sets the movement keys held from the next tick on.
+    pub fn set_input(&mut self, input: MovementInput) {
+        self.input = input;
+    }
+
+    pub fn look(&mut self, yaw: f32, pitch: f32) {
+        self.physics.yaw = yaw;
+        self.physics.pitch = pitch.clamp(-90.0, 90.0);
+    }
+
+    /// Finds a path from the block the player is standing in to `goal`.
+    pub fn find_path(&self, goal: Position, config: &PathConfig) -> anyhow::Result<Path> {
+        let (x, y, z) = self.physics.position();
+        let start = Position {
+            x: x.floor() as i32,
+            y: (y + 1e-3).floor() as i32,
+            z: z.floor() as i32,
+        };
+        pathfind::find_path(&self.world, start, goal, config)
+    }
+
+    /// Walks along a path, overriding the input set with `set_input` until it is finished or
+    /// `stop` is called.
+    pub fn follow_path(&mut self, path: Path) {
+        self.follower = Some(PathFollower::new(path));
+    }
+
+    /// Stops following the current path and releases all movement keys.
+    pub fn stop(&mut self) {
+        self.follower = None;
+        self.input = MovementInput::default();
+    }
+
+    pub fn is_following(&self) -> bool {
+        self.follower.is_some()
+    }
+
+    /// The final value of one of the player's attributes, such as
+    /// `minecraft:generic.movement_speed`.
+    pub fn attribute(&self, key: &str) -> Option<f64> {
+        self.properties.get(key).map(EntityProperty::final_value)
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn entities(&self) -> &EntityTracker {
+        &self.entities
+    }
+
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    /// The commands the server has declared; empty until `DeclareCommands` is received.
+    pub fn commands(&self) -> &CommandGraph {
+        &self.commands
+    }
+
+    pub fn recipes(&self) -> &RecipeBook {
+        &self.recipes
+    }
+
+    pub fn advancements(&self) -> &AdvancementTracker {
+        &self.advancements
+    }
+
+    /// How many packets of each ID were skipped because they could not be decoded.
+    pub fn skipped_packets(&self) -> &HashMap<i32, u64> {
+        &self.skipped_packets
+    }
+
+    pub fn send_chat(&mut self, message: &str) -> anyhow::Result<()> {
+        self.session.write_packet(&Serverbound::ChatMessage {
+            message: message.to_string().into(),
+        })
+    }
+
+    pub fn respawn(&mut self) -> anyhow::Result<()> {
+        self.session
+            .write_packet(&Serverbound::ClientStatus { action: 0.into() })
+    }
+
+    /// Moves a stack between two slots of the current window.
+    pub fn move_stack(&mut self, from: i16, to: i16) -> anyhow::Result<()> {
+        for packet in self.inventory.move_stack(from, to) {
+            self.session.write_packet(&packet)?;
         }
+        Ok(())
+    }
+
+    pub fn shift_click(&mut self, slot: i16) -> anyhow::Result<()> {
+        let packet = self.inventory.shift_click(slot);
+        self.session.write_packet(&packet)
+    }
+
+    pub fn drop_item(&mut self, slot: i16, whole_stack: bool) -> anyhow::Result<()> {
+        let packet = self.inventory.drop_item(slot, whole_stack);
+        self.session.write_packet(&packet)
+    }
+
+    /// Swaps a slot of the current window with a hotbar slot, numbered 0 to 8.
+    pub fn swap_with_hotbar(&mut self, slot: i16, hotbar: u8) -> anyhow::Result<()> {
+        let packet = self.inventory.swap_with_hotbar(slot, hotbar);
+        self.session.write_packet(&packet)
+    }
+
+    pub fn close_window(&mut self) -> anyhow::Result<()> {
+        let packet = self.inventory.close_window();
+        self.session.write_packet(&packet)
+    }
+
+    /// Runs one tick of movement and sends the resulting position. Call this every 50 ms.
+    ///
+    /// Nothing is simulated until the server has sent the initial position, or while the chunk
+    /// the player is in is not loaded.
+    pub fn tick(&mut self) -> anyhow::Result<()> {
+        let (x, _, z) = self.physics.position();
+        let chunk = ((x.floor() as i32) >> 4, (z.floor() as i32) >> 4);
+        if !self.position_known || !self.world.is_loaded(chunk.0, chunk.1) {
+            return Ok(());
+        }
+        if let Some(speed) = self.base_movement_speed() {
+            self.physics.movement_speed = speed;
+        }
+        if let Some(follower) = &mut self.follower {
+            match follower.steer(&self.physics) {
+                Some((input, yaw)) => {
+                    self.input = input;
+                    self.physics.yaw = yaw;
+                }
+                None => self.stop(),
+            }
+        }
+        self.physics.tick(&self.world, &self.input);
+        self.send_movement()
+    }
+
+    /// The movement speed attribute without the sprinting modifier, which the simulation
+    /// applies itself.
+    fn base_movement_speed(&self) -> Option<f64> {
+        let mut property = self.properties.get(MOVEMENT_SPEED)?.clone();
+        property
+            .modifiers
+            .retain(|modifier| modifier.uuid != SPRINTING_SPEED_BOOST);
+        Some(property.final_value())
+    }
+
+    /// Sends the state changes and position packets the vanilla client would send this tick.
+    fn send_movement(&mut self) -> anyhow::Result<()> {
+        let physics = &self.physics;
+        if physics.sprinting != self.last_sent.sprinting {
+            let action_id = if physics.sprinting {
+                START_SPRINTING
+            } else {
+                STOP_SPRINTING
+            };
+            self.session.write_packet(&Serverbound::EntityAction {
+                entity_id: self.entity_id.into(),
+                action_id: action_id.into(),
+                jump_boost: 0.into(),
+            })?;
+            self.last_sent.sprinting = physics.sprinting;
+        }
+        if physics.sneaking != self.last_sent.sneaking {
+            let action_id = if physics.sneaking {
+                START_SNEAKING
+            } else {
+                STOP_SNEAKING
+            };
+            self.session.write_packet(&Serverbound::EntityAction {
+                entity_id: self.entity_id.into(),
+                action_id: action_id.into(),
+                jump_boost: 0.into(),
+            })?;
+            self.last_sent.sneaking = physics.sneaking;
+        }
+
+        let (x, y, z) = physics.position();
+        let (yaw, pitch, on_ground) = (physics.yaw, physics.pitch, physics.on_ground);
+        let last = &mut self.last_sent;
+        let (dx, dy, dz) = (x - last.x, y - last.y, z - last.z);
+        last.ticks_since_position += 1;
+        let moved = dx * dx + dy * dy + dz * dz > 9.0e-4 || last.ticks_since_position >= 20;
+        let rotated = yaw != last.yaw || pitch != last.pitch;
+
+        let packet = if moved && rotated {
+            Serverbound::PlayerPositionAndRotation {
+                x,
+                y,
+                z,
+                yaw,
+                pitch,
+                on_ground,
+            }
+        } else if moved {
+            Serverbound::PlayerPosition { x, y, z, on_ground }
+        } else if rotated {
+            Serverbound::PlayerRotation {
+                yaw,
+                pitch,
+                on_ground,
+            }
+        } else if on_ground != last.on_ground {
+            Serverbound::PlayerMovement { on_ground }
+        } else {
+            return Ok(());
+        };
+        if moved {
+            last.x = x;
+            last.y = y;
+            last.z = z;
+            last.ticks_since_position = 0;
+        }
+        if rotated {
+            last.yaw = yaw;
+            last.pitch = pitch;
+        }
+        last.on_ground = on_ground;
+        self.session.write_packet(&packet)
+    }
+
+    fn spawn_entity(&mut self, entity: Entity, velocity: (i16, i16, i16)) -> Event {
+        let event = Event::EntitySpawned {
+            entity_id: entity.id,
+            type_id: entity.type_id,
+            x: entity.x,
+            y: entity.y,
+            z: entity.z,
+        };
+        let id = entity.id;
+        self.entities.spawn(entity);
+        self.entities.set_velocity(id, velocity);
+        event
+    }
+
+    /// Decodes and handles a packet. Packets that cannot be decoded, because their ID is
+    /// unknown or their fields do not match, or whose contents cannot be applied to the game
+    /// state, are counted and reported instead of failing.
+    pub(crate) fn handle_frame(&mut self, frame: PacketFrame) -> anyhow::Result<Option<Event>> {
+        let error = match frame.decode() {
+            Ok(packet) => match self.handle_packet(&packet) {
+                Err(error) if error.downcast_ref::<InvalidPacket>().is_some() => error,
+                result => return result,
+            },
+            Err(error) => error,
+        };
+        *self.skipped_packets.entry(frame.id).or_default() += 1;
+        Ok(Some(Event::PacketSkipped {
+            frame,
+            reason: format!("{:#}", error),
+        }))
     }
 
     fn handle_packet(&mut self, packet: &Clientbound) -> anyhow::Result<Option<Event>> {
@@ -65,6 +404,12 @@ where
                 self.gamemode = *gamemode;
                 self.view_distance = view_distance.0;
                 self.enable_respawn_screen = *enable_respawn_screen;
+                self.world.clear();
+                self.world.set_view_distance(view_distance.0);
+                self.entities.clear();
+                self.inventory.clear();
+                self.position_known = false;
+                self.follower = None;
 
                 //TODO make these actual settings
                 self.session.write_packet(&Serverbound::ClientSettings {
@@ -75,12 +420,303 @@ where
                     displayed_skin_parts: 0x7f,
                     main_hand: 0.into(),
                 })?;
+                return Ok(Some(Event::Joined));
+            }
+            Clientbound::Disconnect { reason } => {
+                return Ok(Some(Event::Disconnected {
+                    reason: reason.clone(),
+                }));
+            }
+            Clientbound::ChatMessage {
+                json_data,
+                position,
+                sender,
+            } => {
+                return Ok(Some(Event::ChatReceived {
+                    message: json_data.clone(),
+                    position: *position,
+                    sender: *sender,
+                }));
+            }
+            &Clientbound::UpdateHealth {
+                health,
+                food,
+                food_saturation,
+            } => {
+                self.health = health;
+                self.food = food.0;
+                self.food_saturation = food_saturation;
+                return Ok(Some(Event::HealthChanged {
+                    health,
+                    food: food.0,
+                    food_saturation,
+                }));
+            }
+            Clientbound::CombatEvent {
+                event:
+                    CombatEvent::EntityDead {
+                        player_id, message, ..
+                    },
+            } if player_id.0 == self.entity_id => {
+                return Ok(Some(Event::Died {
+                    message: message.clone(),
+                }));
+            }
+            Clientbound::Respawn { gamemode, .. } => {
+                self.gamemode = gamemode_from_id(*gamemode);
+                self.world.clear();
+                self.entities.clear();
+                self.position_known = false;
+                self.follower = None;
+                return Ok(Some(Event::Respawned));
+            }
+            &Clientbound::ChangeGameState { reason: 3, value } => {
+                self.gamemode = gamemode_from_id(value as u8);
+                return Ok(Some(Event::GamemodeChanged {
+                    gamemode: self.gamemode,
+                }));
             }
             Clientbound::HeldItemChange { slot } => {
                 self.held_item = *slot;
+                return Ok(Some(Event::HeldItemChanged { slot: *slot }));
             }
-            Clientbound::DeclareRecipes { .. } => {
-                //TODO registries
+            Clientbound::WindowItems {
+                window_id,
+                slot_data,
+            } => {
+                self.inventory.set_window_items(*window_id, slot_data);
+                return Ok(Some(Event::InventoryChanged {
+                    window_id: *window_id,
+                    slots: (0..).zip(slot_data.iter().cloned()).collect(),
+                }));
+            }
+            Clientbound::SetSlot {
+                window_id,
+                slot,
+                slot_data,
+            } => {
+                self.inventory
+                    .set_slot(*window_id, *slot, slot_data.clone());
+                return Ok(Some(Event::InventoryChanged {
+                    window_id: *window_id,
+                    slots: vec![(*slot, slot_data.clone())],
+                }));
+            }
+            Clientbound::OpenWindow {
+                window_id,
+                window_type,
+                window_title,
+            } => {
+                self.inventory
+                    .open(window_id.0 as u8, window_type.0, window_title.clone());
+                return Ok(Some(Event::WindowOpened {
+                    window_id: window_id.0 as u8,
+                    window_type: window_type.0,
+                    title: window_title.clone(),
+                }));
+            }
+            &Clientbound::CloseWindow { window_id } => {
+                self.inventory.close();
+                return Ok(Some(Event::WindowClosed { window_id }));
+            }
+            &Clientbound::WindowProperty {
+                window_id,
+                property,
+                value,
+            } => {
+                self.inventory.set_property(window_id, property, value);
+            }
+            &Clientbound::WindowConfirmation {
+                window_id,
+                action_number,
+                accepted,
+            } => {
+                if let Some(packet) = self.inventory.confirm(window_id, action_number, accepted) {
+                    self.session.write_packet(&packet)?;
+                }
+            }
+            &Clientbound::TimeUpdate {
+                world_age,
+                time_of_day,
+            } => {
+                return Ok(Some(Event::TimeUpdate {
+                    world_age,
+                    time_of_day,
+                }));
+            }
+            &Clientbound::SpawnEntity {
+                entity_id,
+                object_uuid,
+                ref type_,
+                x,
+                y,
+                z,
+                pitch,
+                yaw,
+                velocity_x,
+                velocity_y,
+                velocity_z,
+                ..
+            } => {
+                let mut entity = Entity::new(entity_id.0, Some(object_uuid), type_.0, x, y, z);
+                entity.yaw = yaw.degrees();
+                entity.pitch = pitch.degrees();
+                return Ok(Some(
+                    self.spawn_entity(entity, (velocity_x, velocity_y, velocity_z)),
+                ));
+            }
+            &Clientbound::SpawnLivingEntity {
+                entity_id,
+                entity_uuid,
+                ref type_,
+                x,
+                y,
+                z,
+                yaw,
+                pitch,
+                head_pitch,
+                velocity_x,
+                velocity_y,
+                velocity_z,
+            } => {
+                let mut entity = Entity::new(entity_id.0, Some(entity_uuid), type_.0, x, y, z);
+                entity.yaw = yaw.degrees();
+                entity.pitch = pitch.degrees();
+                entity.head_yaw = head_pitch.degrees();
+                return Ok(Some(
+                    self.spawn_entity(entity, (velocity_x, velocity_y, velocity_z)),
+                ));
+            }
+            &Clientbound::SpawnPlayer {
+                entity_id,
+                player_uuid,
+                x,
+                y,
+                z,
+                yaw,
+                pitch,
+            } => {
+                let mut entity =
+                    Entity::new(entity_id.0, Some(player_uuid), entity::PLAYER, x, y, z);
+                entity.yaw = yaw.degrees();
+                entity.pitch = pitch.degrees();
+                return Ok(Some(self.spawn_entity(entity, (0, 0, 0))));
+            }
+            &Clientbound::SpawnExperienceOrb {
+                entity_id, x, y, z, ..
+            } => {
+                let entity = Entity::new(entity_id.0, None, entity::EXPERIENCE_ORB, x, y, z);
+                return Ok(Some(self.spawn_entity(entity, (0, 0, 0))));
+            }
+            &Clientbound::SpawnPainting {
+                entity_id,
+                entity_uuid,
+                location,
+                ..
+            } => {
+                let entity = Entity::new(
+                    entity_id.0,
+                    Some(entity_uuid),
+                    entity::PAINTING,
+                    location.x as f64,
+                    location.y as f64,
+                    location.z as f64,
+                );
+                return Ok(Some(self.spawn_entity(entity, (0, 0, 0))));
+            }
+            Clientbound::DestroyEntities { entity_ids } => {
+                for entity_id in entity_ids {
+                    self.entities.remove(entity_id.0);
+                }
+                return Ok(Some(Event::EntitiesRemoved {
+                    entity_ids: entity_ids.iter().map(|id| id.0).collect(),
+                }));
+            }
+            &Clientbound::EntityPosition {
+                entity_id,
+                delta_x,
+                delta_y,
+                delta_z,
+                on_ground,
+            } => {
+                self.entities
+                    .move_relative(entity_id.0, (delta_x, delta_y, delta_z), on_ground);
+            }
+            &Clientbound::EntityPositionAndRotation {
+                entity_id,
+                delta_x,
+                delta_y,
+                delta_z,
+                yaw,
+                pitch,
+                on_ground,
+            } => {
+                self.entities
+                    .move_relative(entity_id.0, (delta_x, delta_y, delta_z), on_ground);
+                self.entities.rotate(entity_id.0, yaw, pitch);
+            }
+            &Clientbound::EntityRotation {
+                entity_id,
+                yaw,
+                pitch,
+                ..
+            } => {
+                self.entities.rotate(entity_id.0, yaw, pitch);
+            }
+            &Clientbound::EntityTeleport {
+                entity_id,
+                x,
+                y,
+                z,
+                yaw,
+                pitch,
+                on_ground,
+            } => {
+                self.entities.teleport(entity_id.0, x, y, z, on_ground);
+                self.entities.rotate(entity_id.0, yaw, pitch);
+            }
+            &Clientbound::EntityVelocity {
+                entity_id,
+                velocity_x,
+                velocity_y,
+                velocity_z,
+            } => {
+                self.entities
+                    .set_velocity(entity_id.0, (velocity_x, velocity_y, velocity_z));
+            }
+            Clientbound::EntityMetadata {
+                entity_id,
+                metadata,
+            } => {
+                self.entities.update_metadata(entity_id.0, metadata);
+            }
+            Clientbound::EntityEquipment {
+                entity_id,
+                equipment,
+            } => {
+                self.entities.update_equipment(entity_id.0, equipment);
+            }
+            Clientbound::EntityProperties {
+                entity_id,
+                properties,
+            } => {
+                if entity_id.0 == self.entity_id {
+                    for property in properties {
+                        self.properties
+                            .insert(property.key.0.clone().into(), property.clone());
+                    }
+                } else {
+                    self.entities.update_properties(entity_id.0, properties);
+                }
+            }
+            &Clientbound::EntityHeadLook {
+                entity_id,
+                head_yaw,
+            } => {
+                self.entities.set_head_yaw(entity_id.0, head_yaw);
+            }
+            Clientbound::DeclareRecipes { recipes } => {
+                self.recipes.declare(recipes);
             }
             Clientbound::Tags { .. } => {
                 //TODO registries
@@ -88,11 +724,32 @@ where
             Clientbound::EntityStatus { .. } => {
                 //TODO
             }
-            Clientbound::DeclareCommands { .. } => {
-                //TODO
+            Clientbound::DeclareCommands { nodes, root_index } => {
+                self.commands = CommandGraph::new(nodes.clone(), root_index.0 as usize)
+                    .context(InvalidPacket)?;
             }
-            Clientbound::UnlockRecipes { .. } => {
-                //TODO
+            Clientbound::Advancements {
+                reset,
+                advancements,
+                removed,
+                progress,
+            } => {
+                let completed = self
+                    .advancements
+                    .update(*reset, advancements, removed, progress);
+                if !completed.is_empty() {
+                    return Ok(Some(Event::AdvancementsCompleted {
+                        advancements: completed,
+                    }));
+                }
+            }
+            Clientbound::UnlockRecipes {
+                action,
+                recipe_list_1,
+                recipe_list_2,
+                ..
+            } => {
+                self.recipes.unlock(action.0, recipe_list_1, recipe_list_2);
             }
             &Clientbound::PlayerPositionAndLook {
                 x,
@@ -103,51 +760,131 @@ where
                 flags,
                 teleport_id,
             } => {
+                let physics = &mut self.physics;
                 if flags & 0x01 == 0 {
-                    self.x = x;
+                    physics.x = x;
+                    physics.velocity.0 = 0.0;
                 } else {
-                    self.x += x;
+                    physics.x += x;
                 }
                 if flags & 0x02 == 0 {
-                    self.y = y;
+                    physics.y = y;
+                    physics.velocity.1 = 0.0;
                 } else {
-                    self.y += y;
+                    physics.y += y;
                 }
                 if flags & 0x04 == 0 {
-                    self.z = z;
+                    physics.z = z;
+                    physics.velocity.2 = 0.0;
                 } else {
-                    self.z += z;
+                    physics.z += z;
                 }
                 if flags & 0x08 == 0 {
-                    self.yaw = yaw;
+                    physics.yaw = yaw;
                 } else {
-                    self.yaw += yaw;
+                    physics.yaw += yaw;
                 }
                 if flags & 0x10 == 0 {
-                    self.pitch = pitch;
+                    physics.pitch = pitch;
                 } else {
-                    self.pitch += pitch;
+                    physics.pitch += pitch;
                 }
+                self.position_known = true;
+                self.last_sent = LastSent {
+                    x: physics.x,
+                    y: physics.y,
+                    z: physics.z,
+                    yaw: physics.yaw,
+                    pitch: physics.pitch,
+                    on_ground: false,
+                    ..self.last_sent
+                };
+
                 self.session
                     .write_packet(&Serverbound::TeleportConfirm { teleport_id })?;
                 self.session
                     .write_packet(&Serverbound::PlayerPositionAndRotation {
-                        x: self.x,
-                        y: self.y,
-                        z: self.z,
-                        yaw: self.yaw,
-                        pitch: self.pitch,
-                        on_ground: self.on_ground,
+                        x: physics.x,
+                        y: physics.y,
+                        z: physics.z,
+                        yaw: physics.yaw,
+                        pitch: physics.pitch,
+                        on_ground: false,
                     })?;
+                return Ok(Some(Event::Teleported {
+                    x: physics.x,
+                    y: physics.y,
+                    z: physics.z,
+                    yaw: physics.yaw,
+                    pitch: physics.pitch,
+                }));
             }
             Clientbound::PlayerInfo { .. } => {
                 //TODO
             }
-            Clientbound::UpdateLight { .. } => {
-                //TODO
+            Clientbound::UpdateLight {
+                chunk_x,
+                chunk_z,
+                sky_light_mask,
+                block_light_mask,
+                empty_sky_light_mask,
+                empty_block_light_mask,
+                sky_light_arrays,
+                block_light_arrays,
+                ..
+            } => {
+                self.world
+                    .update_light(
+                        chunk_x.0,
+                        chunk_z.0,
+                        sky_light_mask.0,
+                        block_light_mask.0,
+                        empty_sky_light_mask.0,
+                        empty_block_light_mask.0,
+                        sky_light_arrays,
+                        block_light_arrays,
+                    )
+                    .context(InvalidPacket)?;
             }
-            Clientbound::ChunkData { .. } => {
-                //TODO
+            Clientbound::ChunkData {
+                chunk_x,
+                chunk_z,
+                full_chunk,
+                primary_bit_mask,
+                biomes,
+                data,
+                ..
+            } => {
+                self.world
+                    .load_chunk(
+                        *chunk_x,
+                        *chunk_z,
+                        *full_chunk,
+                        primary_bit_mask.0,
+                        biomes,
+                        data,
+                    )
+                    .context(InvalidPacket)?;
+            }
+            &Clientbound::UnloadChunk { chunk_x, chunk_z } => {
+                self.world.unload_chunk(chunk_x, chunk_z);
+            }
+            &Clientbound::BlockChange { location, block_id } => {
+                self.world.set_block(location, block_id.0);
+            }
+            Clientbound::MultiBlockChange {
+                chunk_section,
+                records,
+                ..
+            } => {
+                self.world.apply_multi_block_change(*chunk_section, records);
+            }
+            &Clientbound::UpdateViewPosition { chunk_x, chunk_z } => {
+                self.world.set_view_position(chunk_x.0, chunk_z.0);
+            }
+            &Clientbound::UpdateViewDistance { view_distance } => {
+                self.view_distance = view_distance.0;
+                self.world.set_view_distance(view_distance.0);
             }
             Clientbound::WorldBorder { .. } => {
                 //TODO
@@ -160,12 +897,17 @@ where
         Ok(None)
     }
 
+    #[cfg(feature = "async")]
+    pub(crate) fn session_mut(&mut self) -> &mut TransportSession<R, W> {
+        &mut self.session
+    }
+
     pub fn try_poll(&mut self) -> anyhow::Result<Option<Event>>
     where
         R: Peekable,
     {
-        while let Some(packet) = self.session.try_read_packet()? {
-            if let Some(event) = self.handle_packet(&packet)? {
+        while let Some(frame) = self.session.try_read_frame()? {
+            if let Some(event) = self.handle_frame(frame)? {
                 return Ok(Some(event));
             }
         }
@@ -174,12 +916,202 @@ where
 
     pub fn poll(&mut self) -> anyhow::Result<Event> {
         loop {
-            let packet = self.session.read_packet()?;
-            if let Some(event) = self.handle_packet(&packet)? {
+            let frame = self.session.read_frame()?;
+            if let Some(event) = self.handle_frame(frame)? {
                 return Ok(event);
             }
         }
     }
 }
 
-pub enum Event {}
+const MOVEMENT_SPEED: &str = "minecraft:generic.movement_speed";
+const SPRINTING_SPEED_BOOST: Uuid = Uuid(0x662a6b8d_da3e_4c1c_8813_96ea6097278d);
+
+// Actions of `EntityAction`.
+const START_SNEAKING: i32 = 0;
+const STOP_SNEAKING: i32 = 1;
+const START_SPRINTING: i32 = 3;
+const STOP_SPRINTING: i32 = 4;
+
+/// The movement state last reported to the server.
+#[derive(Debug, Clone, Copy, Default)]
+struct LastSent {
+    x: f64,
+    y: f64,
+    z: f64,
+    yaw: f32,
+    pitch: f32,
+    on_ground: bool,
+    sprinting: bool,
+    sneaking: bool,
+    ticks_since_position: u32,
+}
+
+fn gamemode_from_id(id: u8) -> Gamemode {
+    match id {
+        1 => Gamemode::Creative,
+        2 => Gamemode::Adventure,
+        3 => Gamemode::Spectator,
+        _ => Gamemode::Survival,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Joined,
+    Disconnected {
+        reason: Chat,
+    },
+    ChatReceived {
+        message: Chat,
+        position: i8,
+        sender: Uuid,
+    },
+    HealthChanged {
+        health: f32,
+        food: i32,
+        food_saturation: f32,
+    },
+    Died {
+        message: Chat,
+    },
+    Respawned,
+    GamemodeChanged {
+        gamemode: Gamemode,
+    },
+    Teleported {
+        x: f64,
+        y: f64,
+        z: f64,
+        yaw: f32,
+        pitch: f32,
+    },
+    EntitySpawned {
+        entity_id: i32,
+        type_id: i32,
+        x: f64,
+        y: f64,
+        z: f64,
+    },
+    EntitiesRemoved {
+        entity_ids: Vec<i32>,
+    },
+    HeldItemChanged {
+        slot: i8,
+    },
+    InventoryChanged {
+        window_id: u8,
+        slots: Vec<(i16, Slot)>,
+    },
+    TimeUpdate {
+        world_age: i64,
+        time_of_day: i64,
+    },
+    WindowOpened {
+        window_id: u8,
+        window_type: i32,
+        title: Chat,
+    },
+    WindowClosed {
+        window_id: u8,
+    },
+    /// Advancements completed since the last update, excluding those loaded on join.
+    AdvancementsCompleted {
+        advancements: Vec<String>,
+    },
+    /// A packet that could not be decoded or applied, and was ignored.
+    PacketSkipped {
+        frame: PacketFrame,
+        reason: String,
+    },
+}
+
+/// Context for errors from packets that decoded but could not be applied to the game state,
+/// as opposed to I/O errors, so that they can be skipped.
+#[derive(Debug)]
+struct InvalidPacket;
+
+impl fmt::Display for InvalidPacket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid packet contents")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::{Nbt, Value};
+    use serde_json::json;
+
+    #[test]
+    fn skips_undecodable_packets() {
+        let mut incoming = Vec::new();
+        // An unknown ID, and a keep-alive with a trailing byte.
+        PacketFrame::new(0x7f, vec![1, 2, 3])
+            .write(&mut incoming, None)
+            .unwrap();
+        PacketFrame::new(0x1f, vec![0; 9])
+            .write(&mut incoming, None)
+            .unwrap();
+        let disconnect = Clientbound::Disconnect {
+            reason: Chat(json!("bye")),
+        };
+        PacketFrame::from_packet(&disconnect)
+            .unwrap()
+            .write(&mut incoming, None)
+            .unwrap();
+
+        let session = TransportSession::new(incoming.as_slice(), Vec::new());
+        let mut play = Play::new(session, Uuid(0), "bot".to_string());
+        match play.poll().unwrap() {
+            Event::PacketSkipped { frame, .. } => assert_eq!(frame.id, 0x7f),
+            event => panic!("unexpected event: {:?}", event),
+        }
+        match play.poll().unwrap() {
+            Event::PacketSkipped { frame, reason } => {
+                assert_eq!(frame.id, 0x1f);
+                assert!(reason.contains("left over"));
+            }
+            event => panic!("unexpected event: {:?}", event),
+        }
+        assert_eq!(
+            play.poll().unwrap(),
+            Event::Disconnected {
+                reason: Chat(json!("bye"))
+            }
+        );
+        assert_eq!(play.skipped_packets()[&0x7f], 1);
+        assert_eq!(play.skipped_packets()[&0x1f], 1);
+    }
+
+    #[test]
+    fn skips_packets_that_fail_to_apply() {
+        let mut incoming = Vec::new();
+        let chunk = Clientbound::ChunkData {
+            chunk_x: 0,
+            chunk_z: 0,
+            full_chunk: true,
+            primary_bit_mask: 1.into(),
+            heightmaps: Nbt::new(String::new(), Value::End),
+            biomes: Vec::new(),
+            data: vec![0xff; 4],
+            block_entities: Vec::new(),
+        };
+        PacketFrame::from_packet(&chunk)
+            .unwrap()
+            .write(&mut incoming, None)
+            .unwrap();
+
+        let session = TransportSession::new(incoming.as_slice(), Vec::new());
+        let mut play = Play::new(session, Uuid(0), "bot".to_string());
+        match play.poll().unwrap() {
+            Event::PacketSkipped { frame, reason } => {
+                assert_eq!(frame.id, 0x20);
+                assert!(reason.starts_with("invalid packet contents: "));
+            }
+            event => panic!("unexpected event: {:?}", event),
+        }
+        assert_eq!(play.skipped_packets()[&0x20], 1);
+        assert!(!play.world().is_loaded(0, 0));
+    }
+}
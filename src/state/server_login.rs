@@ -0,0 +1,329 @@
+//! The server's side of the login state, for test servers and proxies.
+
+use crate::auth::{self, GameProfile, SessionService};
+use crate::proto::login::{Clientbound, Serverbound};
+use crate::proto::types::{Chat, Uuid};
+use crate::proto::TransportSession;
+use anyhow::Context;
+use rsa::{PaddingScheme, PublicKeyParts, RSAPrivateKey};
+use serde_json::json;
+use std::convert::TryInto;
+use std::io;
+use std::net::TcpStream;
+
+/// The key pair that logins are encrypted with, usually generated once and used for every
+/// connection.
+pub struct ServerKey {
+    private_key: RSAPrivateKey,
+    public_key_der: Vec<u8>,
+}
+
+impl ServerKey {
+    pub fn new(private_key: RSAPrivateKey) -> Self {
+        let public_key_der = public_key_der(&private_key);
+        Self {
+            private_key,
+            public_key_der,
+        }
+    }
+
+    /// Generates a 1024-bit key, like the vanilla server.
+    pub fn generate() -> anyhow::Result<Self> {
+        Ok(Self::new(RSAPrivateKey::new(
+            &mut rand::thread_rng(),
+            1024,
+        )?))
+    }
+
+    /// The public key, as sent to clients in `EncryptionRequest`.
+    pub fn public_key_der(&self) -> &[u8] {
+        &self.public_key_der
+    }
+
+    fn decrypt(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        Ok(self
+            .private_key
+            .decrypt(PaddingScheme::PKCS1v15Encrypt, data)?)
+    }
+}
+
+/// Encodes the public half of a key as an X.509 SubjectPublicKeyInfo structure.
+fn public_key_der(key: &impl PublicKeyParts) -> Vec<u8> {
+    let mut rsa_public_key = Vec::new();
+    der_integer(&mut rsa_public_key, &key.n().to_bytes_be());
+    der_integer(&mut rsa_public_key, &key.e().to_bytes_be());
+
+    // The bit string starts with the number of unused bits in its last byte.
+    let mut bit_string = vec![0];
+    der_element(&mut bit_string, 0x30, &rsa_public_key);
+
+    // The rsaEncryption algorithm identifier, without parameters.
+    let mut info = vec![
+        0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01, 0x05, 0x00,
+    ];
+    der_element(&mut info, 0x03, &bit_string);
+
+    let mut der = Vec::new();
+    der_element(&mut der, 0x30, &info);
+    der
+}
+
+fn der_integer(out: &mut Vec<u8>, big_endian: &[u8]) {
+    // Integers are signed, so positive ones need a leading zero if their top bit is set.
+    if big_endian[0] & 0x80 != 0 {
+        let mut padded = vec![0];
+        padded.extend_from_slice(big_endian);
+        der_element(out, 0x02, &padded);
+    } else {
+        der_element(out, 0x02, big_endian);
+    }
+}
+
+fn der_element(out: &mut Vec<u8>, tag: u8, contents: &[u8]) {
+    out.push(tag);
+    if contents.len() < 0x80 {
+        out.push(contents.len() as u8);
+    } else {
+        let len = contents.len().to_be_bytes();
+        let skip = len.iter().take_while(|&&byte| byte == 0).count();
+        out.push(0x80 | (len.len() - skip) as u8);
+        out.extend_from_slice(&len[skip..]);
+    }
+    out.extend_from_slice(contents);
+}
+
+/// Logs in a client, after it has sent a handshake with `NextState::Login`.
+pub struct ServerLogin<R = TcpStream, W = TcpStream> {
+    session: TransportSession<R, W>,
+    compression_threshold: Option<usize>,
+}
+
+impl<R, W> ServerLogin<R, W>
+where
+    R: io::Read,
+    W: io::Write,
+{
+    pub fn new(session: TransportSession<R, W>) -> Self {
+        Self {
+            session,
+            compression_threshold: None,
+        }
+    }
+
+    /// Enables compression of packets of at least `threshold` bytes before login succeeds.
+    pub fn set_compression_threshold(&mut self, threshold: Option<usize>) {
+        self.compression_threshold = threshold;
+    }
+
+    /// Logs the player in without verifying them, like a server with `online-mode=false`.
+    pub fn accept_offline(mut self) -> anyhow::Result<(TransportSession<R, W>, GameProfile)> {
+        let name = self.read_login_start()?;
+        let profile = GameProfile {
+            id: format!("{:032x}", auth::offline_uuid(&name).0),
+            name,
+            properties: Vec::new(),
+        };
+        self.finish(profile)
+    }
+
+    /// Encrypts the connection and verifies the player with the session server, like a server
+    /// with `online-mode=true`.
+    ///
+    /// The returned session is encrypted and in the play state.
+    pub fn accept(
+        mut self,
+        key: &ServerKey,
+        session_service: &SessionService,
+    ) -> anyhow::Result<(TransportSession<R, W>, GameProfile)> {
+        let name = self.read_login_start()?;
+
+        let verify_token: [u8; 4] = rand::random();
+        self.session.write_packet(&Clientbound::EncryptionRequest {
+            server_id: String::new().into(),
+            public_key_der: key.public_key_der.clone(),
+            verify_token: verify_token.to_vec(),
+        })?;
+        let (shared_secret, verify_token_encrypted) = match self.session.read_packet()? {
+            Serverbound::EncryptionResponse {
+                shared_secret,
+                verify_token,
+            } => (shared_secret, verify_token),
+            _ => return Err(anyhow::Error::msg("unexpected packet from client")),
+        };
+        if key.decrypt(&verify_token_encrypted)? != verify_token {
+            return Err(anyhow::Error::msg("verify token mismatch"));
+        }
+        let shared_secret: [u8; 16] = key
+            .decrypt(&shared_secret)?
+            .as_slice()
+            .try_into()
+            .context("shared secret has the wrong length")?;
+        self.session.enable_encryption(shared_secret)?;
+
+        let server_hash = auth::server_hash("", &shared_secret, &key.public_key_der);
+        match session_service.has_joined(&name, &server_hash)? {
+            Some(profile) => self.finish(profile),
+            None => {
+                self.session.write_packet(&Clientbound::Disconnect {
+                    reason: Chat(json!("Failed to verify username!")),
+                })?;
+                Err(anyhow::Error::msg(format!(
+                    "{} has not joined the session",
+                    name
+                )))
+            }
+        }
+    }
+
+    fn read_login_start(&mut self) -> anyhow::Result<String> {
+        match self.session.read_packet()? {
+            Serverbound::LoginStart { name } => Ok(name.into()),
+            _ => Err(anyhow::Error::msg("unexpected packet from client")),
+        }
+    }
+
+    fn finish(
+        mut self,
+        profile: GameProfile,
+    ) -> anyhow::Result<(TransportSession<R, W>, GameProfile)> {
+        let uuid = u128::from_str_radix(&profile.id, 16).context("invalid UUID in profile")?;
+        if let Some(threshold) = self.compression_threshold {
+            let threshold: i32 = threshold.try_into()?;
+            self.session.write_packet(&Clientbound::SetCompression {
+                threshold: threshold.into(),
+            })?;
+            self.session
+                .set_compression_threshold(self.compression_threshold);
+        }
+        self.session.write_packet(&Clientbound::LoginSuccess {
+            uuid: Uuid(uuid),
+            username: profile.name.clone().into(),
+        })?;
+        Ok((self.session, profile))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::test_server::{route, TestServer};
+    use crate::auth::{AuthService, Authentication};
+    use crate::state::Login;
+    use std::net::TcpListener;
+    use std::thread;
+
+    type ServerResult = anyhow::Result<(TransportSession, GameProfile)>;
+
+    /// Connects a client session to a server login running `server` on another thread.
+    fn loopback<F>(server: F) -> (TransportSession, thread::JoinHandle<ServerResult>)
+    where
+        F: FnOnce(ServerLogin) -> ServerResult + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            server(ServerLogin::new(TransportSession::new(
+                stream.try_clone().unwrap(),
+                stream,
+            )))
+        });
+        let stream = TcpStream::connect(addr).unwrap();
+        (
+            TransportSession::new(stream.try_clone().unwrap(), stream),
+            server,
+        )
+    }
+
+    #[test]
+    fn online_login() {
+        let profile = json!({
+            "id": "069a79f444e94726a5befca90e38aaf5",
+            "name": "Notch",
+            "properties": [{"name": "textures", "value": "e30=", "signature": "c2ln"}],
+        });
+        let http = TestServer::start(vec![
+            route(
+                "/authenticate",
+                200,
+                json!({
+                    "accessToken": "access",
+                    "clientToken": "client",
+                    "selectedProfile": {"id": "069a79f444e94726a5befca90e38aaf5", "name": "Notch"},
+                }),
+            ),
+            route("/validate", 204, json!({})),
+            route("/session/minecraft/join", 204, json!({})),
+            route("/session/minecraft/hasJoined", 200, profile),
+        ]);
+        let auth_service = AuthService::new(http.url());
+        let session_service = SessionService::new(http.url());
+
+        let key = ServerKey::new(RSAPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap());
+        let server_session = session_service.clone();
+        let (session, server) = loopback(move |mut login| {
+            login.set_compression_threshold(Some(256));
+            login.accept(&key, &server_session)
+        });
+
+        let mut auth = auth_service
+            .authenticate("notch@example.com", "hunter2")
+            .unwrap();
+        let play = Login::new(session)
+            .login_with(&mut auth, &auth_service, &session_service)
+            .unwrap();
+        assert_eq!(play.username(), "Notch");
+        assert_eq!(play.uuid(), &Uuid(0x069a79f444e94726a5befca90e38aaf5));
+
+        let (_, profile) = server.join().unwrap().unwrap();
+        assert_eq!(profile.name, "Notch");
+        assert_eq!(profile.properties[0].signature.as_deref(), Some("c2ln"));
+
+        let requests = http.requests();
+        let join = requests[2].json();
+        let server_hash = join["serverId"].as_str().unwrap();
+        assert_eq!(
+            requests[3].path,
+            format!(
+                "/session/minecraft/hasJoined?username=Notch&serverId={}",
+                server_hash
+            )
+        );
+    }
+
+    #[test]
+    fn offline_login() {
+        let (session, server) = loopback(ServerLogin::accept_offline);
+        let play = Login::new(session)
+            .login(&mut Authentication::offline("Notch"))
+            .unwrap();
+        assert_eq!(play.uuid(), &auth::offline_uuid("Notch"));
+
+        let (_, profile) = server.join().unwrap().unwrap();
+        assert_eq!(profile.name, "Notch");
+        assert_eq!(profile.id, "b50ad385829d3141a2167e7d7539ba7f");
+    }
+
+    #[test]
+    fn offline_client_rejects_encryption() {
+        let http = TestServer::start(Vec::new());
+        let auth_service = AuthService::new(http.url());
+        let session_service = SessionService::new(http.url());
+
+        let key = ServerKey::new(RSAPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap());
+        let server_session = session_service.clone();
+        let (session, server) = loopback(move |login| login.accept(&key, &server_session));
+
+        let error = match Login::new(session).login_with(
+            &mut Authentication::offline("Notch"),
+            &auth_service,
+            &session_service,
+        ) {
+            Ok(_) => panic!("offline login succeeded"),
+            Err(error) => error,
+        };
+        assert!(error.to_string().contains("needs an online account"));
+        assert!(server.join().unwrap().is_err());
+        assert!(http.requests().is_empty());
+    }
+}
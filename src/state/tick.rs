@@ -0,0 +1,193 @@
+use crate::proto::Peekable;
+use crate::state::{Event, Play};
+use std::io;
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The length of a game tick.
+pub const TICK_DURATION: Duration = Duration::from_millis(50);
+
+/// If the loop falls this many ticks behind, the missed ticks are dropped instead of being run
+/// back to back.
+const MAX_CATCH_UP_TICKS: u32 = 20;
+
+type TickCallback<R, W> = Box<dyn FnMut(&mut Play<R, W>, u64) -> anyhow::Result<()>>;
+
+/// Drives a `Play` state at a fixed 20 ticks per second.
+///
+/// Each tick drains the packets that have arrived, runs the registered callbacks and then
+/// `Play::tick`, which simulates movement and sends the resulting position.
+pub struct TickLoop<R = TcpStream, W = TcpStream> {
+    play: Play<R, W>,
+    callbacks: Vec<TickCallback<R, W>>,
+    clock: TickClock,
+    tick_count: u64,
+    tick_time: Duration,
+    disconnected: bool,
+}
+
+impl<R, W> TickLoop<R, W>
+where
+    R: io::Read + Peekable,
+    W: io::Write,
+{
+    pub fn new(play: Play<R, W>) -> Self {
+        Self {
+            play,
+            callbacks: Vec::new(),
+            clock: TickClock::new(Instant::now()),
+            tick_count: 0,
+            tick_time: Duration::from_secs(0),
+            disconnected: false,
+        }
+    }
+
+    pub fn play(&self) -> &Play<R, W> {
+        &self.play
+    }
+
+    pub fn play_mut(&mut self) -> &mut Play<R, W> {
+        &mut self.play
+    }
+
+    pub fn into_inner(self) -> Play<R, W> {
+        self.play
+    }
+
+    /// The number of ticks run so far.
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
+
+    /// How late the last tick started compared to the fixed schedule.
+    pub fn lag(&self) -> Duration {
+        self.clock.lag
+    }
+
+    /// How long the last tick took to run, excluding the wait before it.
+    pub fn tick_time(&self) -> Duration {
+        self.tick_time
+    }
+
+    /// The number of ticks dropped because the loop fell too far behind.
+    pub fn skipped_ticks(&self) -> u64 {
+        self.clock.skipped
+    }
+
+    /// Registers a callback run every tick, after incoming packets are handled and before
+    /// movement is simulated. It receives the current tick count.
+    pub fn on_tick<F>(&mut self, callback: F)
+    where
+        F: FnMut(&mut Play<R, W>, u64) -> anyhow::Result<()> + 'static,
+    {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// Waits for the next scheduled tick and runs it, returning the events received since the
+    /// previous one.
+    ///
+    /// Once the server disconnects, the remaining packets are not read and nothing more is
+    /// sent.
+    pub fn step(&mut self) -> anyhow::Result<Vec<Event>> {
+        if self.disconnected {
+            return Ok(Vec::new());
+        }
+        let wait = self.clock.wait_time(Instant::now());
+        if wait > Duration::from_secs(0) {
+            thread::sleep(wait);
+        }
+        let started = Instant::now();
+        self.clock.start_tick(started);
+
+        let mut events = Vec::new();
+        while let Some(event) = self.play.try_poll()? {
+            let disconnected = matches!(event, Event::Disconnected { .. });
+            events.push(event);
+            if disconnected {
+                self.disconnected = true;
+                return Ok(events);
+            }
+        }
+
+        for callback in &mut self.callbacks {
+            callback(&mut self.play, self.tick_count)?;
+        }
+        self.play.tick()?;
+
+        self.tick_count += 1;
+        self.tick_time = started.elapsed();
+        Ok(events)
+    }
+
+    /// Runs ticks until the server disconnects, passing every event to `handler`.
+    pub fn run<F>(&mut self, mut handler: F) -> anyhow::Result<()>
+    where
+        F: FnMut(&mut Play<R, W>, Event) -> anyhow::Result<()>,
+    {
+        while !self.disconnected {
+            for event in self.step()? {
+                handler(&mut self.play, event)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The fixed tick schedule.
+#[derive(Debug, Clone)]
+struct TickClock {
+    next_tick: Instant,
+    lag: Duration,
+    skipped: u64,
+}
+
+impl TickClock {
+    fn new(now: Instant) -> Self {
+        Self {
+            next_tick: now,
+            lag: Duration::from_secs(0),
+            skipped: 0,
+        }
+    }
+
+    fn wait_time(&self, now: Instant) -> Duration {
+        self.next_tick.saturating_duration_since(now)
+    }
+
+    /// Records that a tick started at `now` and schedules the next one.
+    fn start_tick(&mut self, now: Instant) {
+        self.lag = now.saturating_duration_since(self.next_tick);
+        if self.lag > TICK_DURATION * MAX_CATCH_UP_TICKS {
+            self.skipped += (self.lag.as_nanos() / TICK_DURATION.as_nanos()) as u64;
+            self.next_tick = now;
+        }
+        self.next_tick += TICK_DURATION;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_schedule() {
+        let start = Instant::now();
+        let mut clock = TickClock::new(start);
+        assert_eq!(clock.wait_time(start), Duration::from_secs(0));
+        clock.start_tick(start);
+        assert_eq!(clock.wait_time(start), TICK_DURATION);
+
+        // Late by 30 ms: the next tick stays on schedule.
+        let late = start + TICK_DURATION + Duration::from_millis(30);
+        clock.start_tick(late);
+        assert_eq!(clock.lag, Duration::from_millis(30));
+        assert_eq!(clock.wait_time(late), Duration::from_millis(20));
+
+        // Stalled for 5 seconds: missed ticks are dropped.
+        let stalled = late + Duration::from_secs(5);
+        clock.start_tick(stalled);
+        assert_eq!(clock.skipped, 99);
+        assert_eq!(clock.wait_time(stalled), TICK_DURATION);
+    }
+}
@@ -0,0 +1,17 @@
+//! Factories for the proto types that tests build by hand.
+
+use crate::nbt::{Nbt, Value};
+use crate::proto::types::{Identifier, Slot, VarInt};
+
+/// A stack of items without NBT data.
+pub fn item(item_id: i32, item_count: i8) -> Slot {
+    Slot::Present {
+        item_id: VarInt(item_id),
+        item_count,
+        nbt: Nbt::new(String::new(), Value::End),
+    }
+}
+
+pub fn identifier(id: &str) -> Identifier {
+    Identifier(id.to_string().into())
+}
@@ -0,0 +1,541 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: (f64, f64, f64),
+    pub max: (f64, f64, f64),
+}
+
+impl Aabb {
+    pub const FULL_BLOCK: Aabb = Aabb {
+        min: (0.0, 0.0, 0.0),
+        max: (1.0, 1.0, 1.0),
+    };
+
+    pub fn new(min: (f64, f64, f64), max: (f64, f64, f64)) -> Self {
+        Self { min, max }
+    }
+
+    pub fn offset(&self, dx: f64, dy: f64, dz: f64) -> Self {
+        Self {
+            min: (self.min.0 + dx, self.min.1 + dy, self.min.2 + dz),
+            max: (self.max.0 + dx, self.max.1 + dy, self.max.2 + dz),
+        }
+    }
+
+    /// Grows the box in the direction of a movement.
+    pub fn expand_towards(&self, dx: f64, dy: f64, dz: f64) -> Self {
+        let mut expanded = *self;
+        if dx < 0.0 {
+            expanded.min.0 += dx;
+        } else {
+            expanded.max.0 += dx;
+        }
+        if dy < 0.0 {
+            expanded.min.1 += dy;
+        } else {
+            expanded.max.1 += dy;
+        }
+        if dz < 0.0 {
+            expanded.min.2 += dz;
+        } else {
+            expanded.max.2 += dz;
+        }
+        expanded
+    }
+
+    pub fn inflate(&self, amount: f64) -> Self {
+        Self {
+            min: (
+                self.min.0 - amount,
+                self.min.1 - amount,
+                self.min.2 - amount,
+            ),
+            max: (
+                self.max.0 + amount,
+                self.max.1 + amount,
+                self.max.2 + amount,
+            ),
+        }
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.0 < other.max.0
+            && self.max.0 > other.min.0
+            && self.min.1 < other.max.1
+            && self.max.1 > other.min.1
+            && self.min.2 < other.max.2
+            && self.max.2 > other.min.2
+    }
+
+    /// Limits a movement of `self` along X so that it does not enter `other`.
+    pub fn clip_x(&self, other: &Aabb, dx: f64) -> f64 {
+        if self.max.1 <= other.min.1
+            || self.min.1 >= other.max.1
+            || self.max.2 <= other.min.2
+            || self.min.2 >= other.max.2
+        {
+            return dx;
+        }
+        clip(self.min.0, self.max.0, other.min.0, other.max.0, dx)
+    }
+
+    /// Limits a movement of `self` along Y so that it does not enter `other`.
+    pub fn clip_y(&self, other: &Aabb, dy: f64) -> f64 {
+        if self.max.0 <= other.min.0
+            || self.min.0 >= other.max.0
+            || self.max.2 <= other.min.2
+            || self.min.2 >= other.max.2
+        {
+            return dy;
+        }
+        clip(self.min.1, self.max.1, other.min.1, other.max.1, dy)
+    }
+
+    /// Limits a movement of `self` along Z so that it does not enter `other`.
+    pub fn clip_z(&self, other: &Aabb, dz: f64) -> f64 {
+        if self.max.0 <= other.min.0
+            || self.min.0 >= other.max.0
+            || self.max.1 <= other.min.1
+            || self.min.1 >= other.max.1
+        {
+            return dz;
+        }
+        clip(self.min.2, self.max.2, other.min.2, other.max.2, dz)
+    }
+}
+
+fn clip(min: f64, max: f64, other_min: f64, other_max: f64, delta: f64) -> f64 {
+    if delta > 0.0 && max <= other_min {
+        delta.min(other_min - max)
+    } else if delta < 0.0 && min >= other_max {
+        delta.max(other_max - min)
+    } else {
+        delta
+    }
+}
+
+/// How a block state behaves for movement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockShape {
+    /// No collision, such as air, flowers and torches.
+    Empty,
+    Full,
+    /// A single collision box, in block-relative coordinates, such as a slab or carpet.
+    Partial(Aabb),
+    Water,
+    Lava,
+    /// Ladders and vines, which can be climbed. Ladders have a thin collision box against their
+    /// wall, while vines have none, as in vanilla.
+    Climbable(Option<Aabb>),
+}
+
+impl BlockShape {
+    pub fn collision_box(&self) -> Option<Aabb> {
+        match self {
+            Self::Full => Some(Aabb::FULL_BLOCK),
+            Self::Partial(aabb) => Some(*aabb),
+            Self::Climbable(aabb) => *aabb,
+            _ => None,
+        }
+    }
+
+    pub fn is_solid(&self) -> bool {
+        self.collision_box().is_some()
+    }
+
+    pub fn is_climbable(&self) -> bool {
+        matches!(self, Self::Climbable(_))
+    }
+}
+
+/// How the shapes of a block's states follow from their offset from its first state.
+///
+/// Properties are numbered in alphabetical order, with the last one changing fastest, so
+/// blocks with the same properties can share one range.
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Empty,
+    Water,
+    Lava,
+    /// Empty, or water if waterlogged. `waterlogged` must be the last property.
+    Waterloggable,
+    /// A box inset from the sides by the first value and as high as the second, in pixels.
+    Cuboid(f64, f64),
+    Carpet,
+    Snow,
+    Cake,
+    Ladder,
+    Vine,
+    Slab,
+    /// Approximated by the slab-like half of the stairs, which spans the whole block.
+    Stairs,
+    Door,
+    Trapdoor,
+    /// Approximated by one box around the post and all connected sides.
+    Fence,
+    Pane,
+    Wall,
+    FenceGate,
+}
+
+// Block state IDs of the 1.16.2 global palette, from the server's block report. Every other
+// state is a full cube.
+const BLOCKS: &[(RangeInclusive<i32>, Kind)] = &[
+    (0..=0, Kind::Empty),                     // air
+    (21..=32, Kind::Empty),                   // oak_sapling..dark_oak_sapling (6 blocks)
+    (34..=49, Kind::Water),                   // water
+    (50..=65, Kind::Lava),                    // lava
+    (1049..=1304, Kind::Cuboid(0.0, 9.0)),    // white_bed..black_bed (16 blocks)
+    (1305..=1328, Kind::Empty),               // powered_rail, detector_rail
+    (1341..=1344, Kind::Empty),               // cobweb..dead_bush (4 blocks)
+    (1345..=1347, Kind::Water),               // seagrass, tall_seagrass
+    (1412..=1426, Kind::Empty),               // dandelion..red_mushroom (15 blocks)
+    (1435..=1952, Kind::Empty),               // torch..soul_fire (4 blocks)
+    (1954..=2033, Kind::Stairs),              // oak_stairs
+    (2034..=2057, Kind::Cuboid(1.0, 14.0)),   // chest
+    (2058..=3353, Kind::Empty),               // redstone_wire
+    (3357..=3364, Kind::Empty),               // wheat
+    (3365..=3372, Kind::Cuboid(0.0, 15.0)),   // farmland
+    (3381..=3572, Kind::Waterloggable),       // oak_sign..dark_oak_sign (6 blocks)
+    (3573..=3636, Kind::Door),                // oak_door
+    (3637..=3644, Kind::Ladder),              // ladder
+    (3645..=3654, Kind::Empty),               // rail
+    (3655..=3734, Kind::Stairs),              // cobblestone_stairs
+    (3735..=3782, Kind::Waterloggable),       // oak_wall_sign..dark_oak_wall_sign (6 blocks)
+    (3783..=3808, Kind::Empty),               // lever, stone_pressure_plate
+    (3809..=3872, Kind::Door),                // iron_door
+    (3873..=3884, Kind::Empty), // oak_pressure_plate..dark_oak_pressure_plate (6 blocks)
+    (3887..=3920, Kind::Empty), // redstone_torch, redstone_wall_torch, stone_button
+    (3921..=3928, Kind::Snow),  // snow
+    (3931..=3946, Kind::Cuboid(1.0, 15.0)), // cactus
+    (3948..=3963, Kind::Empty), // sugar_cane
+    (3966..=3997, Kind::Fence), // oak_fence
+    (4000..=4000, Kind::Cuboid(0.0, 14.0)), // soul_sand
+    (4008..=4012, Kind::Empty), // soul_torch, soul_wall_torch
+    (4014..=4015, Kind::Empty), // nether_portal
+    (4024..=4030, Kind::Cake),  // cake
+    (4031..=4094, Kind::Cuboid(0.0, 2.0)), // repeater
+    (4111..=4494, Kind::Trapdoor), // oak_trapdoor..dark_oak_trapdoor (6 blocks)
+    (4697..=4728, Kind::Pane),  // iron_bars
+    (4735..=4766, Kind::Pane),  // glass_pane
+    (4768..=4791, Kind::Empty), // attached_pumpkin_stem..melon_stem (4 blocks)
+    (4792..=4823, Kind::Vine),  // vine
+    (4824..=4855, Kind::FenceGate), // oak_fence_gate
+    (4856..=5015, Kind::Stairs), // brick_stairs, stone_brick_stairs
+    (5018..=5018, Kind::Cuboid(1.0, 1.5)), // lily_pad
+    (5020..=5051, Kind::Fence), // nether_brick_fence
+    (5052..=5131, Kind::Stairs), // nether_brick_stairs
+    (5132..=5135, Kind::Empty), // nether_wart
+    (5136..=5136, Kind::Cuboid(0.0, 12.0)), // enchanting_table
+    (5149..=5149, Kind::Empty), // end_portal
+    (5150..=5157, Kind::Cuboid(0.0, 13.0)), // end_portal_frame
+    (5174..=5253, Kind::Stairs), // sandstone_stairs
+    (5255..=5262, Kind::Cuboid(1.0, 14.0)), // ender_chest
+    (5263..=5406, Kind::Empty), // tripwire_hook, tripwire
+    (5408..=5647, Kind::Stairs), // spruce_stairs, birch_stairs, jungle_stairs
+    (5661..=6308, Kind::Wall),  // cobblestone_wall, mossy_cobblestone_wall
+    (6334..=6493, Kind::Empty), // carrots..dark_oak_button (8 blocks)
+    (6626..=6649, Kind::Cuboid(1.0, 14.0)), // trapped_chest
+    (6650..=6681, Kind::Empty), // light_weighted_pressure_plate, heavy_weighted_pressure_plate
+    (6682..=6697, Kind::Cuboid(0.0, 2.0)), // comparator
+    (6698..=6729, Kind::Cuboid(0.0, 6.0)), // daylight_detector
+    (6747..=6826, Kind::Stairs), // quartz_stairs
+    (6827..=6838, Kind::Empty), // activator_rail
+    (6867..=7378, Kind::Pane),  // white_stained_glass_pane..black_stained_glass_pane (16 blocks)
+    (7379..=7538, Kind::Stairs), // acacia_stairs, dark_oak_stairs
+    (7541..=7604, Kind::Trapdoor), // iron_trapdoor
+    (7608..=7847, Kind::Stairs), // prismarine_stairs..dark_prismarine_stairs
+    (7848..=7865, Kind::Slab),  // prismarine_slab, prismarine_brick_slab, dark_prismarine_slab
+    (7870..=7885, Kind::Carpet), // white_carpet..black_carpet (16 blocks)
+    (7889..=8220, Kind::Empty), // sunflower..black_wall_banner (38 blocks)
+    (8224..=8303, Kind::Stairs), // red_sandstone_stairs
+    (8304..=8417, Kind::Slab),  // oak_slab..purpur_slab (19 blocks)
+    (8422..=8581, Kind::FenceGate), // spruce_fence_gate..dark_oak_fence_gate (5 blocks)
+    (8582..=8741, Kind::Fence), // spruce_fence..dark_oak_fence (5 blocks)
+    (8742..=9061, Kind::Door),  // spruce_door..dark_oak_door (5 blocks)
+    (9142..=9221, Kind::Stairs), // purpur_stairs
+    (9223..=9226, Kind::Empty), // beetroots
+    (9227..=9227, Kind::Cuboid(0.0, 15.0)), // grass_path
+    (9228..=9228, Kind::Empty), // end_gateway
+    (9263..=9263, Kind::Empty), // structure_void
+    (9474..=9500, Kind::Water), // kelp, kelp_plant
+    (9524..=9643, Kind::Waterloggable), // dead_tube_coral..horn_coral_wall_fan (30 blocks)
+    (9655..=9655, Kind::Empty), // bamboo_sapling
+    (9669..=9670, Kind::Empty), // void_air, cave_air
+    (9671..=9672, Kind::Water), // bubble_column
+    (9673..=10792, Kind::Stairs), // polished_granite_stairs..diorite_stairs (14 blocks)
+    (10793..=10870, Kind::Slab), // polished_granite_slab..diorite_slab (13 blocks)
+    (10871..=14758, Kind::Wall), // brick_wall..diorite_wall (12 blocks)
+    (14854..=14857, Kind::Cuboid(0.0, 9.0)), // stonecutter
+    (14894..=14957, Kind::Cuboid(0.0, 7.0)), // campfire, soul_campfire
+    (14958..=14961, Kind::Empty), // sweet_berry_bush
+    (14975..=14975, Kind::Empty), // warped_fungus
+    (14977..=14978, Kind::Empty), // warped_roots, nether_sprouts
+    (14992..=14992, Kind::Empty), // crimson_fungus
+    (14994..=15047, Kind::Vine), // weeping_vines..twisting_vines_plant (4 blocks)
+    (15048..=15048, Kind::Empty), // crimson_roots
+    (15051..=15062, Kind::Slab), // crimson_slab, warped_slab
+    (15063..=15066, Kind::Empty), // crimson_pressure_plate, warped_pressure_plate
+    (15067..=15130, Kind::Fence), // crimson_fence, warped_fence
+    (15131..=15258, Kind::Trapdoor), // crimson_trapdoor, warped_trapdoor
+    (15259..=15322, Kind::FenceGate), // crimson_fence_gate, warped_fence_gate
+    (15323..=15482, Kind::Stairs), // crimson_stairs, warped_stairs
+    (15483..=15530, Kind::Empty), // crimson_button, warped_button
+    (15531..=15658, Kind::Door), // crimson_door, warped_door
+    (15659..=15738, Kind::Waterloggable), // crimson_sign..warped_wall_sign (4 blocks)
+    (15828..=15828, Kind::Cuboid(1.0, 15.0)), // honey_block
+    (15844..=15923, Kind::Stairs), // blackstone_stairs
+    (15924..=16247, Kind::Wall), // blackstone_wall
+    (16248..=16253, Kind::Slab), // blackstone_slab
+    (16258..=16263, Kind::Slab), // polished_blackstone_brick_slab
+    (16264..=16343, Kind::Stairs), // polished_blackstone_brick_stairs
+    (16344..=16667, Kind::Wall), // polished_blackstone_brick_wall
+    (16669..=16748, Kind::Stairs), // polished_blackstone_stairs
+    (16749..=16754, Kind::Slab), // polished_blackstone_slab
+    (16755..=16780, Kind::Empty), // polished_blackstone_pressure_plate, polished_blackstone_button
+    (16781..=17104, Kind::Wall), // polished_blackstone_wall
+];
+
+impl Kind {
+    fn shape(self, offset: i32) -> BlockShape {
+        let partial = |min, max| BlockShape::Partial(pixels(min, max));
+        match self {
+            Self::Empty => BlockShape::Empty,
+            Self::Water => BlockShape::Water,
+            Self::Lava => BlockShape::Lava,
+            Self::Waterloggable => {
+                if offset % 2 == 0 {
+                    BlockShape::Water
+                } else {
+                    BlockShape::Empty
+                }
+            }
+            Self::Cuboid(inset, height) => {
+                partial((inset, 0.0, inset), (16.0 - inset, height, 16.0 - inset))
+            }
+            Self::Carpet => partial((0.0, 0.0, 0.0), (16.0, 1.0, 16.0)),
+            Self::Snow => {
+                // The collision box is one layer lower than the snow.
+                let layers = offset + 1;
+                if layers == 1 {
+                    BlockShape::Empty
+                } else {
+                    partial((0.0, 0.0, 0.0), (16.0, (layers - 1) as f64 * 2.0, 16.0))
+                }
+            }
+            Self::Cake => {
+                let bites = offset as f64;
+                partial((1.0 + bites * 2.0, 0.0, 1.0), (15.0, 8.0, 15.0))
+            }
+            Self::Ladder => BlockShape::Climbable(Some(thin_box(offset / 2))),
+            Self::Vine => BlockShape::Climbable(None),
+            Self::Slab => half_block(offset / 2 % 3),
+            Self::Stairs => half_block(offset / 10 % 2),
+            Self::Door => {
+                let facing = offset / 16 % 4;
+                let right_hinge = offset / 4 % 2 == 1;
+                let open = offset / 2 % 2 == 0;
+                let side = match (open, facing, right_hinge) {
+                    (false, _, _) => facing,
+                    (true, NORTH, false) | (true, SOUTH, true) => EAST,
+                    (true, NORTH, true) | (true, SOUTH, false) => WEST,
+                    (true, WEST, false) | (true, EAST, true) => NORTH,
+                    (true, _, _) => SOUTH,
+                };
+                BlockShape::Partial(thin_box(side))
+            }
+            Self::Trapdoor => {
+                let top = offset / 8 % 2 == 0;
+                let open = offset / 4 % 2 == 0;
+                if open {
+                    BlockShape::Partial(thin_box(offset / 16 % 4))
+                } else if top {
+                    partial((0.0, 13.0, 0.0), (16.0, 16.0, 16.0))
+                } else {
+                    partial((0.0, 0.0, 0.0), (16.0, 3.0, 16.0))
+                }
+            }
+            Self::Fence | Self::Pane => {
+                let (post_min, post_max, height) = match self {
+                    Self::Fence => (6.0, 10.0, 24.0),
+                    _ => (7.0, 9.0, 16.0),
+                };
+                let connected = |bit: i32| offset >> bit & 1 == 0;
+                connected_box(
+                    [connected(3), connected(2), connected(0), connected(4)],
+                    post_min,
+                    post_max,
+                    height,
+                )
+            }
+            Self::Wall => {
+                let connected = |place: i32| offset / place % 3 != 0;
+                connected_box(
+                    [connected(36), connected(12), connected(1), connected(108)],
+                    4.0,
+                    12.0,
+                    24.0,
+                )
+            }
+            Self::FenceGate => {
+                let open = offset / 2 % 2 == 0;
+                if open {
+                    BlockShape::Empty
+                } else if offset / 8 % 4 < WEST {
+                    partial((0.0, 0.0, 6.0), (16.0, 24.0, 10.0))
+                } else {
+                    partial((6.0, 0.0, 0.0), (10.0, 24.0, 16.0))
+                }
+            }
+        }
+    }
+}
+
+// Values of the `facing` property of horizontal blocks.
+const NORTH: i32 = 0;
+const SOUTH: i32 = 1;
+const WEST: i32 = 2;
+const EAST: i32 = 3;
+
+/// A box in pixels, sixteenths of a block.
+fn pixels(min: (f64, f64, f64), max: (f64, f64, f64)) -> Aabb {
+    Aabb::new(
+        (min.0 / 16.0, min.1 / 16.0, min.2 / 16.0),
+        (max.0 / 16.0, max.1 / 16.0, max.2 / 16.0),
+    )
+}
+
+/// The upper half, lower half or all of a block, for the `type` of a slab or the `half` of
+/// stairs.
+fn half_block(half: i32) -> BlockShape {
+    match half {
+        0 => BlockShape::Partial(pixels((0.0, 8.0, 0.0), (16.0, 16.0, 16.0))),
+        1 => BlockShape::Partial(pixels((0.0, 0.0, 0.0), (16.0, 8.0, 16.0))),
+        _ => BlockShape::Full,
+    }
+}
+
+/// The box of a ladder, door or open trapdoor facing the given way, against the opposite side
+/// of the block.
+fn thin_box(facing: i32) -> Aabb {
+    match facing {
+        NORTH => pixels((0.0, 0.0, 13.0), (16.0, 16.0, 16.0)),
+        SOUTH => pixels((0.0, 0.0, 0.0), (16.0, 16.0, 3.0)),
+        WEST => pixels((13.0, 0.0, 0.0), (16.0, 16.0, 16.0)),
+        _ => pixels((0.0, 0.0, 0.0), (3.0, 16.0, 16.0)),
+    }
+}
+
+/// The box around a post and the arms connecting it to the north, south, west and east.
+fn connected_box(connected: [bool; 4], post_min: f64, post_max: f64, height: f64) -> BlockShape {
+    let [north, south, west, east] = connected;
+    let min = |connected| if connected { 0.0 } else { post_min };
+    let max = |connected| if connected { 16.0 } else { post_max };
+    BlockShape::Partial(pixels(
+        (min(west), 0.0, min(north)),
+        (max(east), height, max(south)),
+    ))
+}
+
+/// Maps block states to shapes.
+///
+/// The client has no block registry, so shapes of 1.16.2 blocks are built in: non-colliding and
+/// waterlogged blocks, liquids, climbable blocks and the common partial blocks. Every other
+/// state is treated as a full cube. Other shapes can be registered with `set`.
+#[derive(Debug, Clone, Default)]
+pub struct BlockShapes {
+    overrides: HashMap<i32, BlockShape>,
+}
+
+impl BlockShapes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, state: i32) -> BlockShape {
+        if let Some(&shape) = self.overrides.get(&state) {
+            return shape;
+        }
+        let index = BLOCKS.binary_search_by(|(range, _)| {
+            if *range.end() < state {
+                Ordering::Less
+            } else if *range.start() > state {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        });
+        match index {
+            Ok(index) => {
+                let (range, kind) = &BLOCKS[index];
+                kind.shape(state - range.start())
+            }
+            Err(_) => BlockShape::Full,
+        }
+    }
+
+    pub fn set(&mut self, state: i32, shape: BlockShape) {
+        self.overrides.insert(state, shape);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_shapes() {
+        let shapes = BlockShapes::new();
+        let partial = |min, max| BlockShape::Partial(pixels(min, max));
+
+        assert_eq!(shapes.get(1), BlockShape::Full);
+        assert_eq!(shapes.get(1342), BlockShape::Empty);
+        assert_eq!(shapes.get(1345), BlockShape::Water);
+        // Waterlogged and dry oak signs.
+        assert_eq!(shapes.get(3381), BlockShape::Water);
+        assert_eq!(shapes.get(3382), BlockShape::Empty);
+
+        // Top, bottom and double smooth stone slabs.
+        assert_eq!(
+            shapes.get(8347),
+            partial((0.0, 8.0, 0.0), (16.0, 16.0, 16.0))
+        );
+        assert_eq!(
+            shapes.get(8349),
+            partial((0.0, 0.0, 0.0), (16.0, 8.0, 16.0))
+        );
+        assert_eq!(shapes.get(8351), BlockShape::Full);
+        // Bottom oak stairs.
+        assert_eq!(
+            shapes.get(1965),
+            partial((0.0, 0.0, 0.0), (16.0, 8.0, 16.0))
+        );
+
+        assert_eq!(shapes.get(3921), BlockShape::Empty);
+        assert_eq!(
+            shapes.get(3928),
+            partial((0.0, 0.0, 0.0), (16.0, 14.0, 16.0))
+        );
+
+        // A ladder facing north, and a vine.
+        assert_eq!(
+            shapes.get(3638),
+            BlockShape::Climbable(Some(pixels((0.0, 0.0, 13.0), (16.0, 16.0, 16.0))))
+        );
+        assert_eq!(shapes.get(4792), BlockShape::Climbable(None));
+
+        // A closed and an open oak door facing north, hinged on the left.
+        assert_eq!(shapes.get(3584), BlockShape::Partial(thin_box(NORTH)));
+        assert_eq!(shapes.get(3582), BlockShape::Partial(thin_box(EAST)));
+
+        // A cobblestone wall connected to the north.
+        assert_eq!(
+            shapes.get(5700),
+            partial((4.0, 0.0, 0.0), (12.0, 24.0, 12.0))
+        );
+    }
+}
@@ -0,0 +1,383 @@
+use crate::proto::types::VarInt;
+use declio::ctx::Len;
+use declio::{Decode, Encode};
+use std::convert::{TryFrom, TryInto};
+use std::io;
+
+pub const SECTION_WIDTH: usize = 16;
+pub const SECTION_HEIGHT: usize = 16;
+pub const SECTION_VOLUME: usize = SECTION_WIDTH * SECTION_WIDTH * SECTION_HEIGHT;
+pub const SECTIONS_PER_COLUMN: usize = 16;
+
+const MIN_BITS_PER_BLOCK: u8 = 4;
+const MAX_INDIRECT_BITS_PER_BLOCK: u8 = 8;
+const GLOBAL_BITS_PER_BLOCK: u8 = 15;
+
+/// A 16x16x16 cube of block states.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkSection {
+    block_count: i16,
+    palette: Option<Vec<i32>>,
+    data: PackedArray,
+}
+
+impl ChunkSection {
+    pub fn new() -> Self {
+        Self {
+            block_count: 0,
+            palette: Some(vec![0]),
+            data: PackedArray::new(MIN_BITS_PER_BLOCK, SECTION_VOLUME),
+        }
+    }
+
+    pub fn block_count(&self) -> i16 {
+        self.block_count
+    }
+
+    pub fn bits_per_block(&self) -> u8 {
+        self.data.bits()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.block_count == 0
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> i32 {
+        let value = self.data.get(section_index(x, y, z));
+        match &self.palette {
+            Some(palette) => palette.get(value as usize).copied().unwrap_or(0),
+            None => value as i32,
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, state: i32) {
+        let index = section_index(x, y, z);
+        let old_state = self.get(x, y, z);
+        if old_state == state {
+            return;
+        }
+        if old_state == 0 {
+            self.block_count += 1;
+        } else if state == 0 {
+            self.block_count -= 1;
+        }
+
+        if let Some(palette) = &mut self.palette {
+            if !palette.contains(&state) {
+                palette.push(state);
+                if palette.len() > 1 << self.data.bits() {
+                    self.grow();
+                }
+            }
+        }
+        let value = match &self.palette {
+            Some(palette) => palette.iter().position(|&entry| entry == state).unwrap() as u64,
+            None => state as u64,
+        };
+        self.data.set(index, value);
+    }
+
+    /// Increases the size of each entry by one bit, switching to the global palette once the
+    /// indirect palette would exceed the maximum size.
+    fn grow(&mut self) {
+        let bits = self.data.bits() + 1;
+        let mut data;
+        if bits > MAX_INDIRECT_BITS_PER_BLOCK {
+            data = PackedArray::new(GLOBAL_BITS_PER_BLOCK, SECTION_VOLUME);
+            let palette = self.palette.take().unwrap_or_default();
+            for i in 0..SECTION_VOLUME {
+                let state = palette.get(self.data.get(i) as usize).copied().unwrap_or(0);
+                data.set(i, state as u64);
+            }
+        } else {
+            data = PackedArray::new(bits, SECTION_VOLUME);
+            for i in 0..SECTION_VOLUME {
+                data.set(i, self.data.get(i));
+            }
+        }
+        self.data = data;
+    }
+}
+
+impl Default for ChunkSection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Encode for ChunkSection {
+    fn encode<W>(&self, _: (), writer: &mut W) -> Result<(), declio::Error>
+    where
+        W: io::Write,
+    {
+        self.block_count.encode((), writer)?;
+        self.data.bits().encode((), writer)?;
+        if let Some(palette) = &self.palette {
+            VarInt(palette.len().try_into()?).encode((), writer)?;
+            for &state in palette {
+                VarInt(state).encode((), writer)?;
+            }
+        }
+        VarInt(self.data.longs.len().try_into()?).encode((), writer)?;
+        self.data.longs.encode((), writer)?;
+        Ok(())
+    }
+}
+
+impl Decode for ChunkSection {
+    fn decode<R>(_: (), reader: &mut R) -> Result<Self, declio::Error>
+    where
+        R: io::Read,
+    {
+        let block_count = i16::decode((), reader)?;
+        let mut bits = u8::decode((), reader)?;
+        if bits == 0 {
+            return Err(declio::Error::new("invalid bits per block"));
+        }
+
+        let palette = if bits <= MAX_INDIRECT_BITS_PER_BLOCK {
+            bits = bits.max(MIN_BITS_PER_BLOCK);
+            let len = VarInt::decode((), reader)?;
+            let palette: Vec<VarInt> = Vec::decode(Len::try_from(len)?, reader)?;
+            Some(palette.into_iter().map(i32::from).collect())
+        } else {
+            bits = GLOBAL_BITS_PER_BLOCK;
+            None
+        };
+
+        let len = VarInt::decode((), reader)?;
+        let longs: Vec<i64> = Vec::decode(Len::try_from(len)?, reader)?;
+        if longs.len() != PackedArray::long_count(bits, SECTION_VOLUME) {
+            return Err(declio::Error::new("chunk section data has wrong length"));
+        }
+
+        Ok(Self {
+            block_count,
+            palette,
+            data: PackedArray { bits, longs },
+        })
+    }
+}
+
+/// A full-height column of chunk sections.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkColumn {
+    sections: Vec<Option<ChunkSection>>,
+    biomes: Vec<i32>,
+}
+
+impl ChunkColumn {
+    pub fn new() -> Self {
+        Self {
+            sections: vec![None; SECTIONS_PER_COLUMN],
+            biomes: Vec::new(),
+        }
+    }
+
+    pub fn section(&self, y: usize) -> Option<&ChunkSection> {
+        self.sections.get(y).and_then(Option::as_ref)
+    }
+
+    pub fn section_mut(&mut self, y: usize) -> Option<&mut ChunkSection> {
+        self.sections.get_mut(y).and_then(Option::as_mut)
+    }
+
+    pub fn set_section(&mut self, y: usize, section: Option<ChunkSection>) {
+        self.sections[y] = section;
+    }
+
+    pub fn biomes(&self) -> &[i32] {
+        &self.biomes
+    }
+
+    pub fn set_biomes(&mut self, biomes: Vec<i32>) {
+        self.biomes = biomes;
+    }
+
+    /// The bitmask of non-empty sections, as sent in `ChunkData::primary_bit_mask`.
+    pub fn primary_bit_mask(&self) -> i32 {
+        self.sections
+            .iter()
+            .enumerate()
+            .filter(|(_, section)| section.is_some())
+            .fold(0, |mask, (y, _)| mask | 1 << y)
+    }
+
+    /// Block state at the given coordinates, relative to the column's origin.
+    pub fn get(&self, x: usize, y: usize, z: usize) -> i32 {
+        self.section(y / SECTION_HEIGHT)
+            .map(|section| section.get(x, y % SECTION_HEIGHT, z))
+            .unwrap_or(0)
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, state: i32) {
+        let section_y = y / SECTION_HEIGHT;
+        if section_y >= SECTIONS_PER_COLUMN {
+            return;
+        }
+        self.sections[section_y]
+            .get_or_insert_with(ChunkSection::new)
+            .set(x, y % SECTION_HEIGHT, z, state);
+    }
+
+    /// Replaces the sections selected by `primary_bit_mask` with the sections of `other`.
+    pub fn merge(&mut self, primary_bit_mask: i32, other: ChunkColumn) {
+        for (y, section) in other.sections.into_iter().enumerate() {
+            if primary_bit_mask & (1 << y) != 0 {
+                self.sections[y] = section;
+            }
+        }
+        if !other.biomes.is_empty() {
+            self.biomes = other.biomes;
+        }
+    }
+}
+
+impl Default for ChunkColumn {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Encode for ChunkColumn {
+    fn encode<W>(&self, _: (), writer: &mut W) -> Result<(), declio::Error>
+    where
+        W: io::Write,
+    {
+        for section in self.sections.iter().flatten() {
+            section.encode((), writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl Decode<i32> for ChunkColumn {
+    fn decode<R>(primary_bit_mask: i32, reader: &mut R) -> Result<Self, declio::Error>
+    where
+        R: io::Read,
+    {
+        let mut column = Self::new();
+        for y in 0..SECTIONS_PER_COLUMN {
+            if primary_bit_mask & (1 << y) != 0 {
+                column.sections[y] = Some(ChunkSection::decode((), reader)?);
+            }
+        }
+        Ok(column)
+    }
+}
+
+/// An array of fixed-width unsigned integers, packed into longs without spanning longs.
+#[derive(Debug, Clone, PartialEq)]
+struct PackedArray {
+    bits: u8,
+    longs: Vec<i64>,
+}
+
+impl PackedArray {
+    fn new(bits: u8, len: usize) -> Self {
+        Self {
+            bits,
+            longs: vec![0; Self::long_count(bits, len)],
+        }
+    }
+
+    fn long_count(bits: u8, len: usize) -> usize {
+        let per_long = 64 / bits as usize;
+        len.div_ceil(per_long)
+    }
+
+    fn bits(&self) -> u8 {
+        self.bits
+    }
+
+    fn mask(&self) -> u64 {
+        (1 << self.bits) - 1
+    }
+
+    fn get(&self, index: usize) -> u64 {
+        let per_long = 64 / self.bits as usize;
+        let shift = (index % per_long) * self.bits as usize;
+        (self.longs[index / per_long] as u64 >> shift) & self.mask()
+    }
+
+    fn set(&mut self, index: usize, value: u64) {
+        let per_long = 64 / self.bits as usize;
+        let shift = (index % per_long) * self.bits as usize;
+        let mask = self.mask();
+        let long = &mut self.longs[index / per_long];
+        let cleared = *long as u64 & !(mask << shift);
+        *long = (cleared | (value & mask) << shift) as i64;
+    }
+}
+
+fn section_index(x: usize, y: usize, z: usize) -> usize {
+    (y * SECTION_WIDTH + z) * SECTION_WIDTH + x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_indirect_section() {
+        let mut bytes = vec![
+            0x00, 0x01, // block count
+            0x04, // bits per block
+            0x02, 0x00, 0x01, // palette: [air, stone]
+            0x80, 0x02, // data length: 256
+        ];
+        // First block is stone, the rest are air.
+        bytes.extend(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        bytes.resize(bytes.len() + 255 * 8, 0);
+
+        let mut input = bytes.as_slice();
+        let section = ChunkSection::decode((), &mut input).unwrap();
+        assert!(input.is_empty());
+        assert_eq!(section.block_count(), 1);
+        assert_eq!(section.get(0, 0, 0), 1);
+        assert_eq!(section.get(1, 0, 0), 0);
+
+        let mut output = Vec::new();
+        section.encode((), &mut output).unwrap();
+        assert_eq!(output, bytes);
+    }
+
+    #[test]
+    fn palette_growth() {
+        let mut section = ChunkSection::new();
+        for i in 0..SECTION_VOLUME {
+            section.set(i % 16, i / 256, (i / 16) % 16, i as i32 % 300 + 1);
+        }
+        assert_eq!(section.bits_per_block(), GLOBAL_BITS_PER_BLOCK);
+        assert_eq!(section.block_count() as usize, SECTION_VOLUME);
+        for i in 0..SECTION_VOLUME {
+            assert_eq!(
+                section.get(i % 16, i / 256, (i / 16) % 16),
+                i as i32 % 300 + 1
+            );
+        }
+
+        let mut output = Vec::new();
+        section.encode((), &mut output).unwrap();
+        let mut input = output.as_slice();
+        assert_eq!(ChunkSection::decode((), &mut input).unwrap(), section);
+    }
+
+    #[test]
+    fn column_roundtrip() {
+        let mut column = ChunkColumn::new();
+        column.set(3, 70, 5, 42);
+        column.set(15, 255, 15, 7);
+        assert_eq!(column.primary_bit_mask(), 1 << 4 | 1 << 15);
+
+        let mut output = Vec::new();
+        column.encode((), &mut output).unwrap();
+        let mut input = output.as_slice();
+        let decoded = ChunkColumn::decode(column.primary_bit_mask(), &mut input).unwrap();
+        assert!(input.is_empty());
+        assert_eq!(decoded, column);
+        assert_eq!(decoded.get(3, 70, 5), 42);
+        assert_eq!(decoded.get(15, 255, 15), 7);
+        assert_eq!(decoded.get(0, 0, 0), 0);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::proto::play::LightArray;
+
+/// Number of light sections per column; one extra section above and below the world.
+pub const LIGHT_SECTIONS: usize = 18;
+
+const NIBBLE_ARRAY_LEN: usize = 2048;
+
+/// Light levels for a 16x16x16 section, packed as 4-bit values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NibbleArray(Vec<u8>);
+
+impl NibbleArray {
+    pub fn new() -> Self {
+        Self(vec![0; NIBBLE_ARRAY_LEN])
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> anyhow::Result<Self> {
+        if bytes.len() != NIBBLE_ARRAY_LEN {
+            return Err(anyhow::Error::msg("light array has wrong length"));
+        }
+        Ok(Self(bytes))
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> u8 {
+        let index = (y * 16 + z) * 16 + x;
+        let byte = self.0[index / 2];
+        if index & 1 == 0 {
+            byte & 0xf
+        } else {
+            byte >> 4
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Default for NibbleArray {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sky and block light for a chunk column, indexed by section Y + 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkLight {
+    sky: Vec<Option<NibbleArray>>,
+    block: Vec<Option<NibbleArray>>,
+}
+
+impl ChunkLight {
+    pub fn new() -> Self {
+        Self {
+            sky: vec![None; LIGHT_SECTIONS],
+            block: vec![None; LIGHT_SECTIONS],
+        }
+    }
+
+    /// Applies the contents of an `UpdateLight` packet.
+    pub fn update(
+        &mut self,
+        sky_light_mask: i32,
+        block_light_mask: i32,
+        empty_sky_light_mask: i32,
+        empty_block_light_mask: i32,
+        sky_light_arrays: &[LightArray],
+        block_light_arrays: &[LightArray],
+    ) -> anyhow::Result<()> {
+        update_sections(
+            &mut self.sky,
+            sky_light_mask,
+            empty_sky_light_mask,
+            sky_light_arrays,
+        )?;
+        update_sections(
+            &mut self.block,
+            block_light_mask,
+            empty_block_light_mask,
+            block_light_arrays,
+        )?;
+        Ok(())
+    }
+
+    /// Sky light level at the given coordinates, relative to the column's origin.
+    ///
+    /// Returns `None` if no light data has been received for that section.
+    pub fn sky_light(&self, x: usize, y: i32, z: usize) -> Option<u8> {
+        get_light(&self.sky, x, y, z)
+    }
+
+    /// Block light level at the given coordinates, relative to the column's origin.
+    ///
+    /// Returns `None` if no light data has been received for that section.
+    pub fn block_light(&self, x: usize, y: i32, z: usize) -> Option<u8> {
+        get_light(&self.block, x, y, z)
+    }
+}
+
+impl Default for ChunkLight {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn update_sections(
+    sections: &mut [Option<NibbleArray>],
+    mask: i32,
+    empty_mask: i32,
+    arrays: &[LightArray],
+) -> anyhow::Result<()> {
+    let mut arrays = arrays.iter();
+    for (i, section) in sections.iter_mut().enumerate() {
+        if mask & (1 << i) != 0 {
+            let array = arrays
+                .next()
+                .ok_or_else(|| anyhow::Error::msg("missing light array"))?;
+            *section = Some(NibbleArray::from_bytes(array.0.clone())?);
+        } else if empty_mask & (1 << i) != 0 {
+            *section = Some(NibbleArray::new());
+        }
+    }
+    Ok(())
+}
+
+fn get_light(sections: &[Option<NibbleArray>], x: usize, y: i32, z: usize) -> Option<u8> {
+    let index = (y >> 4) + 1;
+    if index < 0 {
+        return None;
+    }
+    sections
+        .get(index as usize)?
+        .as_ref()
+        .map(|array| array.get(x, (y & 15) as usize, z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_light() {
+        let mut bytes = vec![0; NIBBLE_ARRAY_LEN];
+        // (1, 0, 0) in the upper nibble of the first byte; (0, 1, 0) at index 256.
+        bytes[0] = 0xf0;
+        bytes[128] = 0x07;
+
+        let mut light = ChunkLight::new();
+        light
+            .update(0b100, 0b0, 0b10, 0b1, &[LightArray(bytes)], &[])
+            .unwrap();
+
+        // Section index 2 is section Y 1, blocks 16..32.
+        assert_eq!(light.sky_light(0, 16, 0), Some(0));
+        assert_eq!(light.sky_light(1, 16, 0), Some(15));
+        assert_eq!(light.sky_light(0, 17, 0), Some(7));
+        assert_eq!(light.sky_light(0, 0, 0), Some(0));
+        assert_eq!(light.sky_light(0, 32, 0), None);
+        assert_eq!(light.block_light(0, -16, 0), Some(0));
+        assert_eq!(light.block_light(0, 16, 0), None);
+    }
+}
@@ -0,0 +1,365 @@
+pub mod block;
+pub mod chunk;
+pub mod light;
+
+use self::block::{Aabb, BlockShape, BlockShapes};
+use self::chunk::{ChunkColumn, SECTIONS_PER_COLUMN, SECTION_HEIGHT};
+use self::light::ChunkLight;
+use crate::proto::play::LightArray;
+use crate::proto::types::{Position, VarInt, VarLong};
+use declio::Decode;
+use std::collections::HashMap;
+
+/// Client-side view of the blocks in the currently loaded chunks.
+#[derive(Debug, Clone, Default)]
+pub struct World {
+    columns: HashMap<(i32, i32), ChunkColumn>,
+    light: HashMap<(i32, i32), ChunkLight>,
+    view_position: (i32, i32),
+    view_distance: i32,
+    shapes: BlockShapes,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn column(&self, chunk_x: i32, chunk_z: i32) -> Option<&ChunkColumn> {
+        self.columns.get(&(chunk_x, chunk_z))
+    }
+
+    pub fn columns(&self) -> impl Iterator<Item = ((i32, i32), &ChunkColumn)> {
+        self.columns
+            .iter()
+            .map(|(&coords, column)| (coords, column))
+    }
+
+    pub fn is_loaded(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        self.columns.contains_key(&(chunk_x, chunk_z))
+    }
+
+    pub fn view_position(&self) -> (i32, i32) {
+        self.view_position
+    }
+
+    pub fn view_distance(&self) -> i32 {
+        self.view_distance
+    }
+
+    pub fn light(&self, chunk_x: i32, chunk_z: i32) -> Option<&ChunkLight> {
+        self.light.get(&(chunk_x, chunk_z))
+    }
+
+    pub fn clear(&mut self) {
+        self.columns.clear();
+        self.light.clear();
+    }
+
+    /// Loads the sections of a `ChunkData` packet, replacing the column if `full_chunk` is set,
+    /// or updating only the sections in `primary_bit_mask` otherwise. Like the vanilla client,
+    /// this ignores updates to columns that are not loaded.
+    pub fn load_chunk(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        full_chunk: bool,
+        primary_bit_mask: i32,
+        biomes: &[VarInt],
+        mut data: &[u8],
+    ) -> Result<(), declio::Error> {
+        if !full_chunk && !self.columns.contains_key(&(chunk_x, chunk_z)) {
+            return Ok(());
+        }
+        let mut column = ChunkColumn::decode(primary_bit_mask, &mut data)?;
+        column.set_biomes(biomes.iter().map(|&biome| biome.into()).collect());
+        if full_chunk {
+            self.columns.insert((chunk_x, chunk_z), column);
+        } else {
+            if let Some(loaded) = self.columns.get_mut(&(chunk_x, chunk_z)) {
+                loaded.merge(primary_bit_mask, column);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn unload_chunk(&mut self, chunk_x: i32, chunk_z: i32) {
+        self.columns.remove(&(chunk_x, chunk_z));
+        self.light.remove(&(chunk_x, chunk_z));
+    }
+
+    /// Applies the light arrays of an `UpdateLight` packet.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_light(
+        &mut self,
+        chunk_x: i32,
+        chunk_z: i32,
+        sky_light_mask: i32,
+        block_light_mask: i32,
+        empty_sky_light_mask: i32,
+        empty_block_light_mask: i32,
+        sky_light_arrays: &[LightArray],
+        block_light_arrays: &[LightArray],
+    ) -> anyhow::Result<()> {
+        self.light.entry((chunk_x, chunk_z)).or_default().update(
+            sky_light_mask,
+            block_light_mask,
+            empty_sky_light_mask,
+            empty_block_light_mask,
+            sky_light_arrays,
+            block_light_arrays,
+        )
+    }
+
+    /// Sky light level at the given position, or `None` if it is not known.
+    pub fn sky_light_at(&self, position: Position) -> Option<u8> {
+        self.light(position.x >> 4, position.z >> 4)?.sky_light(
+            (position.x & 15) as usize,
+            position.y,
+            (position.z & 15) as usize,
+        )
+    }
+
+    /// Block light level at the given position, or `None` if it is not known.
+    pub fn block_light_at(&self, position: Position) -> Option<u8> {
+        self.light(position.x >> 4, position.z >> 4)?.block_light(
+            (position.x & 15) as usize,
+            position.y,
+            (position.z & 15) as usize,
+        )
+    }
+
+    /// The block state at the given position, or `None` if its chunk is not loaded.
+    pub fn block_at(&self, position: Position) -> Option<i32> {
+        let column = self.column(position.x >> 4, position.z >> 4)?;
+        if position.y < 0 || position.y as usize >= SECTIONS_PER_COLUMN * SECTION_HEIGHT {
+            return Some(0);
+        }
+        Some(column.get(
+            (position.x & 15) as usize,
+            position.y as usize,
+            (position.z & 15) as usize,
+        ))
+    }
+
+    pub fn shapes(&self) -> &BlockShapes {
+        &self.shapes
+    }
+
+    pub fn shapes_mut(&mut self) -> &mut BlockShapes {
+        &mut self.shapes
+    }
+
+    /// The shape of the block at the given position. Blocks in unloaded chunks are solid.
+    pub fn shape_at(&self, position: Position) -> BlockShape {
+        match self.block_at(position) {
+            Some(state) => self.shapes.get(state),
+            None => BlockShape::Full,
+        }
+    }
+
+    /// Collision boxes of the blocks intersecting `area`, in world coordinates.
+    pub fn collision_boxes(&self, area: &Aabb) -> Vec<Aabb> {
+        // Fences and walls reach into the block above them.
+        let below = Aabb::new((area.min.0, area.min.1 - 1.0, area.min.2), area.max);
+        let mut boxes = Vec::new();
+        for position in block_positions(&below) {
+            if let Some(collision_box) = self.shape_at(position).collision_box() {
+                let collision_box =
+                    collision_box.offset(position.x as f64, position.y as f64, position.z as f64);
+                if collision_box.intersects(area) {
+                    boxes.push(collision_box);
+                }
+            }
+        }
+        boxes
+    }
+
+    /// Whether any block intersecting `area` matches `predicate`.
+    pub fn any_shape<F>(&self, area: &Aabb, predicate: F) -> bool
+    where
+        F: Fn(BlockShape) -> bool,
+    {
+        block_positions(area).any(|position| predicate(self.shape_at(position)))
+    }
+
+    /// Sets the block state at the given position. Ignored if its chunk is not loaded.
+    pub fn set_block(&mut self, position: Position, state: i32) {
+        if position.y < 0 {
+            return;
+        }
+        if let Some(column) = self.columns.get_mut(&(position.x >> 4, position.z >> 4)) {
+            column.set(
+                (position.x & 15) as usize,
+                position.y as usize,
+                (position.z & 15) as usize,
+                state,
+            );
+        }
+    }
+
+    /// Applies the records of a `MultiBlockChange` packet.
+    pub fn apply_multi_block_change(&mut self, chunk_section: i64, records: &[VarLong]) {
+        // 63    56 55    48 47    40 39    32 31    24 23    16 15     8 7      0
+        // xxxxxxxx xxxxxxxx xxxxxxzz zzzzzzzz zzzzzzzz zzzzyyyy yyyyyyyy yyyyyyyy
+        let section_x = (chunk_section >> 42) as i32;
+        let section_z = ((chunk_section << 22) >> 42) as i32;
+        let section_y = ((chunk_section << 44) >> 44) as i32;
+
+        for &VarLong(record) in records {
+            // record: block state << 12 | x << 8 | z << 4 | y
+            let state = (record >> 12) as i32;
+            let position = Position {
+                x: section_x * 16 + ((record >> 8) & 15) as i32,
+                z: section_z * 16 + ((record >> 4) & 15) as i32,
+                y: section_y * 16 + (record & 15) as i32,
+            };
+            self.set_block(position, state);
+        }
+    }
+
+    pub fn set_view_distance(&mut self, view_distance: i32) {
+        self.view_distance = view_distance;
+        self.prune();
+    }
+
+    /// Moves the center of the loaded area, dropping columns that fall out of range.
+    pub fn set_view_position(&mut self, chunk_x: i32, chunk_z: i32) {
+        self.view_position = (chunk_x, chunk_z);
+        self.prune();
+    }
+
+    fn prune(&mut self) {
+        // Same storage range as the vanilla client's chunk cache.
+        let range = self.view_distance.max(2) + 3;
+        let (center_x, center_z) = self.view_position;
+        let in_range =
+            |&(x, z): &(i32, i32)| (x - center_x).abs() <= range && (z - center_z).abs() <= range;
+        self.columns.retain(|coords, _| in_range(coords));
+        self.light.retain(|coords, _| in_range(coords));
+    }
+}
+
+/// Positions of the blocks that `area` overlaps.
+fn block_positions(area: &Aabb) -> impl Iterator<Item = Position> {
+    let (min_x, min_y, min_z) = (
+        area.min.0.floor() as i32,
+        area.min.1.floor() as i32,
+        area.min.2.floor() as i32,
+    );
+    let (max_x, max_y, max_z) = (
+        area.max.0.ceil() as i32,
+        area.max.1.ceil() as i32,
+        area.max.2.ceil() as i32,
+    );
+    (min_x..max_x).flat_map(move |x| {
+        (min_y..max_y).flat_map(move |y| (min_z..max_z).map(move |z| Position { x, y, z }))
+    })
+}
+
+/// A flat world of stone up to y = 63 in chunk (0, 0), with the given extra blocks as
+/// `(x, y, z, state)`.
+#[cfg(test)]
+pub(crate) fn test_world(blocks: &[(i32, i32, i32, i32)]) -> World {
+    use declio::Encode;
+
+    const STONE: i32 = 1;
+    let mut column = ChunkColumn::new();
+    for x in 0..16 {
+        for z in 0..16 {
+            column.set(x, 63, z, STONE);
+        }
+    }
+    let mut data = Vec::new();
+    column.encode((), &mut data).unwrap();
+    let mut world = World::new();
+    world
+        .load_chunk(0, 0, true, column.primary_bit_mask(), &[], &data)
+        .unwrap();
+    for &(x, y, z, state) in blocks {
+        world.set_block(Position { x, y, z }, state);
+    }
+    world
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use declio::Encode;
+
+    #[test]
+    fn block_updates() {
+        let mut column = ChunkColumn::new();
+        column.set(0, 64, 0, 1);
+        let mut data = Vec::new();
+        column.encode((), &mut data).unwrap();
+
+        let mut world = World::new();
+        world
+            .load_chunk(-1, 2, true, column.primary_bit_mask(), &[], &data)
+            .unwrap();
+        assert_eq!(
+            world.block_at(Position {
+                x: -16,
+                y: 64,
+                z: 32
+            }),
+            Some(1)
+        );
+        assert_eq!(
+            world.block_at(Position {
+                x: -15,
+                y: 64,
+                z: 32
+            }),
+            Some(0)
+        );
+        assert_eq!(world.block_at(Position { x: 0, y: 64, z: 32 }), None);
+
+        // Partial updates of columns that are not loaded are ignored.
+        world
+            .load_chunk(0, 2, false, column.primary_bit_mask(), &[], &data)
+            .unwrap();
+        assert_eq!(world.block_at(Position { x: 0, y: 64, z: 32 }), None);
+
+        world.set_block(
+            Position {
+                x: -1,
+                y: 10,
+                z: 47,
+            },
+            5,
+        );
+        assert_eq!(
+            world.block_at(Position {
+                x: -1,
+                y: 10,
+                z: 47
+            }),
+            Some(5)
+        );
+
+        // section (-1, 4, 2); block state 9 at (1, 2, 3) within the section
+        let chunk_section = (-1i64 << 42) | (2 << 20) | 4;
+        let record = (9 << 12) | (1 << 8) | (3 << 4) | 2;
+        world.apply_multi_block_change(chunk_section, &[VarLong(record)]);
+        assert_eq!(
+            world.block_at(Position {
+                x: -15,
+                y: 66,
+                z: 35
+            }),
+            Some(9)
+        );
+
+        world.unload_chunk(-1, 2);
+        assert_eq!(
+            world.block_at(Position {
+                x: -16,
+                y: 64,
+                z: 32
+            }),
+            None
+        );
+    }
+}